@@ -0,0 +1,33 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use num::{BigUint, One};
+use pairing::compact_bn256::{Bn256, Fr};
+use r1cs::{Element, Bn128 as Bn128F};
+use r1cs_bellman::{convert_element, Bn256Converter, FieldConverter, SameFieldConverter, BN256_LIMBS};
+
+fn sample_elements() -> Vec<Element<Bn128F>> {
+    (0u32..256)
+        .map(|i| Element::from((BigUint::one() << (i as usize % 250)) + BigUint::from(i)))
+        .collect()
+}
+
+fn bench_conversion(c: &mut Criterion) {
+    let elements = sample_elements();
+    c.bench_function("convert general path", |b| b.iter(|| {
+        for n in &elements {
+            black_box(Bn256Converter::convert_field(black_box(n)));
+        }
+    }));
+    c.bench_function("convert same field", |b| b.iter(|| {
+        for n in &elements {
+            black_box(<SameFieldConverter as FieldConverter<Bn128F, Bn256>>::convert_field(black_box(n)));
+        }
+    }));
+    c.bench_function("convert with stack limbs", |b| b.iter(|| {
+        for n in &elements {
+            black_box(convert_element::<Bn128F, Fr, BN256_LIMBS>(black_box(n)));
+        }
+    }));
+}
+
+criterion_group!(benches, bench_conversion);
+criterion_main!(benches);
@@ -0,0 +1,37 @@
+use std::collections::BTreeMap;
+use bellman::Circuit;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use pairing::compact_bn256::Bn256;
+use r1cs::{Expression, GadgetBuilder, Bn128 as Bn128F};
+use r1cs_bellman::{Bn256Converter, CollectingConstraintSystem, WrappedCircuit};
+
+/// Builds a chain `w_{i+1} = w_i * w_i` over `n` wires, which references every wire index.
+fn dense_circuit(n: usize) -> WrappedCircuit<Bn128F, Bn256, Bn256Converter> {
+    let mut builder = GadgetBuilder::<Bn128F>::new();
+    let w = builder.wires(n);
+    for pair in w.windows(2) {
+        let exp = Expression::from(&pair[0]);
+        builder.assert_product(&exp, &exp, &Expression::from(&pair[1]));
+    }
+    WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![w[n - 1]])
+}
+
+fn bench_dense_allocation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("synthesize dense circuit");
+    for n in [1_000, 10_000, 100_000].iter() {
+        let circuit = dense_circuit(*n);
+        for &preallocate_all in &[false, true] {
+            let name = if preallocate_all { "preallocate all" } else { "lazy" };
+            let circuit = circuit.with_witness_map(BTreeMap::new()).with_preallocate_all(preallocate_all);
+            group.bench_with_input(BenchmarkId::new(name, n), n, |b, _| b.iter(|| {
+                let mut cs = CollectingConstraintSystem::<Bn256>::new();
+                circuit.with_witness_map(BTreeMap::new()).synthesize(&mut cs).unwrap();
+                cs
+            }));
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_dense_allocation);
+criterion_main!(benches);
@@ -0,0 +1,49 @@
+use std::collections::BTreeMap;
+use bellman::Circuit;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use num::BigUint;
+use pairing::compact_bn256::Bn256;
+use r1cs::{Element, Expression, GadgetBuilder, Wire, Bn128 as Bn128F};
+use r1cs_bellman::{Bn256Converter, CollectingConstraintSystem, WrappedCircuit};
+
+const TABLE_SIZE: usize = 64;
+
+/// Builds `lookups` one-hot lookups into a table of `TABLE_SIZE` entries, each written as a dense
+/// row in which only the entry for the lookup's index is nonzero: `x_j * 1 = sum_i t_ij s_i`.
+fn lookup_circuit(lookups: usize) -> WrappedCircuit<Bn128F, Bn256, Bn256Converter> {
+    let mut builder = GadgetBuilder::<Bn128F>::new();
+    let selectors = builder.wires(TABLE_SIZE);
+    let mut outputs = Vec::new();
+    for j in 0..lookups {
+        let x = builder.wire();
+        let row: BTreeMap<Wire, Element<Bn128F>> = selectors.iter().enumerate()
+            .map(|(i, s)| {
+                let coeff = if i == j % TABLE_SIZE {
+                    Element::from(BigUint::from(i + 1))
+                } else {
+                    Element::zero()
+                };
+                (*s, coeff)
+            })
+            .collect();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&Wire::ONE), &Expression::new(row));
+        outputs.push(x);
+    }
+    WrappedCircuit::new(builder.build(), BTreeMap::new(), outputs)
+}
+
+fn bench_lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("synthesize one-hot lookups");
+    for lookups in [10, 100, 1_000].iter() {
+        let circuit = lookup_circuit(*lookups);
+        group.bench_with_input(BenchmarkId::from_parameter(lookups), lookups, |b, _| b.iter(|| {
+            let mut cs = CollectingConstraintSystem::<Bn256>::new();
+            circuit.with_witness_map(BTreeMap::new()).synthesize(&mut cs).unwrap();
+            cs
+        }));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_lookup);
+criterion_main!(benches);
@@ -0,0 +1,35 @@
+use std::collections::BTreeMap;
+use bellman::Circuit;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use num::BigUint;
+use pairing::compact_bn256::Bn256;
+use r1cs::{Element, Expression, GadgetBuilder, Wire, Bn128 as Bn128F};
+use r1cs_bellman::{Bn256Converter, CollectingConstraintSystem, WrappedCircuit};
+
+/// Builds `x * 1 = 1 w_1 + 2 w_2 + ... + n w_n`.
+fn many_term_circuit(n: usize) -> WrappedCircuit<Bn128F, Bn256, Bn256Converter> {
+    let mut builder = GadgetBuilder::<Bn128F>::new();
+    let x = builder.wire();
+    let w = builder.wires(n);
+    let sum: BTreeMap<Wire, Element<Bn128F>> = w.iter().enumerate()
+        .map(|(i, wire)| (*wire, Element::from(BigUint::from(i + 1))))
+        .collect();
+    builder.assert_product(&Expression::from(&x), &Expression::from(&Wire::ONE), &Expression::new(sum));
+    WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![x])
+}
+
+fn bench_many_terms(c: &mut Criterion) {
+    let mut group = c.benchmark_group("synthesize many-term C");
+    for n in [100, 1_000, 10_000].iter() {
+        let circuit = many_term_circuit(*n);
+        group.bench_with_input(BenchmarkId::from_parameter(n), n, |b, _| b.iter(|| {
+            let mut cs = CollectingConstraintSystem::<Bn256>::new();
+            circuit.with_witness_map(BTreeMap::new()).synthesize(&mut cs).unwrap();
+            cs
+        }));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_many_terms);
+criterion_main!(benches);
@@ -0,0 +1,39 @@
+use std::collections::BTreeMap;
+use bellman::groth16::{prepare_verifying_key, verify_proof};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pairing::compact_bn256::Bn256;
+use r1cs::{Element, Expression, GadgetBuilder, Bn128 as Bn128F};
+use r1cs_bellman::{Bn256Converter, CachedVerifier, FieldConverter, Groth16Prover, WrappedCircuit};
+use rand::thread_rng;
+
+fn bench_verification(c: &mut Criterion) {
+    let mut builder = GadgetBuilder::<Bn128F>::new();
+    let x = builder.wire();
+    let y = builder.wire();
+    let z = builder.wire();
+    builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+    let circuit: WrappedCircuit<Bn128F, Bn256, Bn256Converter> =
+        WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![z]);
+
+    let rng = &mut thread_rng();
+    let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+    let mut witness_map = BTreeMap::new();
+    for (wire, n) in &[(x, 2u8), (y, 3), (z, 6)] {
+        witness_map.insert(wire.index, Bn256Converter::convert_field(&Element::from(*n)));
+    }
+    let proof = prover.prove(witness_map, rng).unwrap();
+    let public_inputs = [Bn256Converter::convert_field(&Element::from(6u8))];
+    let vk = &prover.parameters().vk;
+
+    c.bench_function("verify, preparing each time", |b| b.iter(|| {
+        let pvk = prepare_verifying_key(black_box(vk));
+        verify_proof(&pvk, black_box(&proof), &public_inputs).unwrap()
+    }));
+    let verifier = CachedVerifier::new(vk);
+    c.bench_function("verify with cached key", |b| b.iter(|| {
+        verifier.verify(black_box(&proof), &public_inputs).unwrap()
+    }));
+}
+
+criterion_group!(benches, bench_verification);
+criterion_main!(benches);
@@ -0,0 +1,39 @@
+use std::collections::{BTreeMap, HashMap};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use pairing::compact_bn256::Fr;
+use pairing::ff::PrimeField;
+
+const WIRES: u32 = 1 << 16;
+
+/// Wire indices in the order a typical gadget first references them: mostly ascending, with some
+/// backward references to earlier intermediate values.
+fn first_use_pattern() -> Vec<u32> {
+    (1..WIRES).flat_map(|i| vec![i, i / 2 + 1]).collect()
+}
+
+fn bench_witness_lookup(c: &mut Criterion) {
+    let value = Fr::from_str("7").unwrap();
+    let btree: BTreeMap<u32, Fr> = (1..WIRES).map(|i| (i, value)).collect();
+    let hash: HashMap<u32, Fr> = btree.iter().map(|(i, v)| (*i, *v)).collect();
+    let sorted: Vec<(u32, Fr)> = btree.iter().map(|(i, v)| (*i, *v)).collect();
+    let pattern = first_use_pattern();
+
+    c.bench_function("witness lookup BTreeMap", |b| b.iter(|| {
+        for i in &pattern {
+            black_box(btree.get(i));
+        }
+    }));
+    c.bench_function("witness lookup HashMap", |b| b.iter(|| {
+        for i in &pattern {
+            black_box(hash.get(i));
+        }
+    }));
+    c.bench_function("witness lookup sorted Vec", |b| b.iter(|| {
+        for i in &pattern {
+            black_box(sorted.binary_search_by_key(i, |(j, _)| *j).ok().map(|k| &sorted[k].1));
+        }
+    }));
+}
+
+criterion_group!(benches, bench_witness_lookup);
+criterion_main!(benches);
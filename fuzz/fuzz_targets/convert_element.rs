@@ -0,0 +1,24 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use num::BigUint;
+use pairing::compact_bn256::{Bn256, Fr};
+use r1cs::{Element, Field, Bn128};
+use r1cs_bellman::{convert_element, fr_from_biguint, Bn256Converter, ModulusCheck, BN256_LIMBS};
+
+fuzz_target!(|data: &[u8]| {
+    let n = BigUint::from_bytes_le(data);
+
+    // An arbitrary integer converts exactly when it is less than the modulus, and fails cleanly
+    // otherwise.
+    let modulus = Bn128::order();
+    assert_eq!(fr_from_biguint::<Fr>(&n).is_some(), n < modulus);
+    let check = ModulusCheck::with_modulus(modulus.clone());
+
+    // A field element always converts, and every conversion path agrees.
+    let element = Element::<Bn128>::from(n % &modulus);
+    let converted = convert_element::<Bn128, Fr, BN256_LIMBS>(&element)
+        .expect("a field element failed to convert");
+    let checked = check.convert::<Bn128, Bn256, Bn256Converter>(&element)
+        .expect("a field element failed the modulus check");
+    assert_eq!(converted, checked);
+});
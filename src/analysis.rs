@@ -0,0 +1,414 @@
+use std::collections::{BTreeMap, BTreeSet};
+use num::BigUint;
+use pairing::Engine;
+use r1cs::{Constraint, Element, Expression, Field, Gadget, Wire};
+
+use crate::{ConditionalConstraint, FieldConverter, WrappedCircuit};
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Counts, for each wire other than the constant wire, how many `(constraint, side)` positions
+    /// reference it. A conditional constraint's selector counts as a fourth side. A public wire
+    /// referenced only once may indicate an under-constrained circuit.
+    pub fn wire_reference_counts(&self) -> BTreeMap<Wire, usize> {
+        let mut counts = BTreeMap::new();
+        let conditionals = self.conditional_constraints().iter().map(|conditional| &conditional.constraint);
+        for constraint in self.gadget().constraints.iter().chain(conditionals) {
+            for exp in &[&constraint.a, &constraint.b, &constraint.c] {
+                for wire in exp.coefficients().keys().filter(|wire| **wire != Wire::ONE) {
+                    *counts.entry(*wire).or_insert(0) += 1;
+                }
+            }
+        }
+        for conditional in self.conditional_constraints() {
+            *counts.entry(conditional.selector).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Returns the indices of the constraints which reference both `a` and `b`, in any of their
+    /// three expressions.
+    pub fn constraints_with_wires(&self, a: Wire, b: Wire) -> Vec<usize> {
+        self.gadget().constraints.iter().enumerate()
+            .filter(|(_, constraint)| {
+                let references = |wire: Wire| [&constraint.a, &constraint.b, &constraint.c].iter()
+                    .any(|exp| exp.coefficients().contains_key(&wire));
+                references(a) && references(b)
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Returns the referenced wires which no constraint structurally constrains, in order. A wire
+    /// is constrained by a constraint if it has a nonzero coefficient in `c`, or in `a` or `b`
+    /// while the other side isn't identically zero. So a wire which only appears in, say,
+    /// `x * 0 = 0` can take any value, and is likely a soundness hole.
+    ///
+    /// This is an audit aid, not a proof of soundness: a wire can be constrained structurally but
+    /// still have more than one satisfying value, such as `x * x = 1`.
+    pub fn free_wires(&self) -> Vec<Wire> {
+        let mut constrained = BTreeSet::new();
+        let conditionals = self.conditional_constraints().iter().map(|conditional| &conditional.constraint);
+        for Constraint { a, b, c } in self.gadget().constraints.iter().chain(conditionals) {
+            let mut sides = vec![c];
+            if !is_zero(b) {
+                sides.push(a);
+            }
+            if !is_zero(a) {
+                sides.push(b);
+            }
+            for exp in sides {
+                constrained.extend(exp.coefficients().iter()
+                    .filter(|(_, coeff)| **coeff != Element::zero())
+                    .map(|(wire, _)| *wire));
+            }
+        }
+        // A selector multiplies `t - c`, where `t` is a fresh variable, so it's always constrained.
+        constrained.extend(self.conditional_constraints().iter().map(|conditional| conditional.selector));
+        self.referenced_wires().into_iter()
+            .filter(|wire| !constrained.contains(wire))
+            .collect()
+    }
+
+    /// Groups wires which the constraints directly force to be equal. Only constraints of the form
+    /// `k x * l = k y`, or `(k x - k y) * l = 0`, for nonzero constants `k` and `l`, are
+    /// recognized, though equalities are followed transitively. Each class has at least two wires,
+    /// and is sorted, as are the classes themselves.
+    pub fn find_equality_classes(&self) -> Vec<Vec<Wire>> {
+        let mut parent: BTreeMap<Wire, Wire> = BTreeMap::new();
+        for (x, y) in self.gadget().constraints.iter().filter_map(equality) {
+            let (x, y) = (find(&parent, x), find(&parent, y));
+            if x != y {
+                parent.insert(x.max(y), x.min(y));
+            }
+        }
+        let mut classes: BTreeMap<Wire, Vec<Wire>> = BTreeMap::new();
+        for wire in parent.keys() {
+            classes.entry(find(&parent, *wire)).or_insert_with(Vec::new).push(*wire);
+        }
+        classes.into_iter()
+            .map(|(root, mut class)| {
+                class.push(root);
+                class.sort();
+                class
+            })
+            .collect()
+    }
+
+    /// Returns an equivalent circuit in which each class found by `find_equality_classes` is merged
+    /// into a single wire, and the equality constraints between them are dropped. Each class is
+    /// represented by its public input, if it has one, or else by its lowest wire. Classes with
+    /// more than one public input are left unmerged.
+    ///
+    /// Merging changes the wire layout, and so the fingerprint: parameters generated for the
+    /// original circuit can't be used with the merged one. Merged-away wires no longer appear in
+    /// the constraints, so their witnesses are simply ignored.
+    ///
+    /// Panics if the circuit has raw constraints, since they refer to the original variables.
+    pub fn merge_equal_wires(&self) -> Self {
+        assert!(self.raw_constraints().is_empty(), "can't merge wires in a circuit with raw constraints");
+        let mut representatives = BTreeMap::new();
+        for class in self.find_equality_classes() {
+            let public: Vec<Wire> = class.iter().cloned()
+                .filter(|wire| self.public_inputs().contains(wire))
+                .collect();
+            if public.len() > 1 {
+                continue;
+            }
+            let representative = public.first().cloned().unwrap_or(class[0]);
+            for wire in class {
+                if wire != representative {
+                    representatives.insert(wire, representative);
+                }
+            }
+        }
+        let substitute = |exp: &Expression<F>| substitute(exp, &representatives);
+        let substitute_constraint = |constraint: &Constraint<F>| Constraint {
+            a: substitute(&constraint.a),
+            b: substitute(&constraint.b),
+            c: substitute(&constraint.c),
+        };
+
+        let merged: BTreeSet<Wire> = representatives.keys().cloned().collect();
+        let constraints = self.gadget().constraints.iter()
+            .filter(|constraint| match equality(constraint) {
+                Some((x, y)) => !merged.contains(&x) && !merged.contains(&y),
+                None => true,
+            })
+            .map(substitute_constraint)
+            .collect();
+        let gadget = Gadget { constraints, witness_generators: Vec::new() };
+        let mut circuit = self.with_witness_map(self.witness_map().clone());
+        circuit.gadget = gadget;
+        circuit.preconverted = None;
+        circuit.conditional_constraints = self.conditional_constraints().iter()
+            .map(|conditional| ConditionalConstraint {
+                constraint: substitute_constraint(&conditional.constraint),
+                selector: *representatives.get(&conditional.selector).unwrap_or(&conditional.selector),
+            })
+            .collect();
+        circuit
+    }
+}
+
+/// A constraint found by `rewrite_shared_products` to have the same product `a * b` as an earlier
+/// one, and replaced by a linear constraint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SharedProductRewrite {
+    /// The index of the earlier constraint, which is kept.
+    pub original: usize,
+    /// The index of the rewritten constraint.
+    pub rewritten: usize,
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Finds constraints `a * b = c2` whose product repeats an earlier constraint's `a * b = c1`,
+    /// in either order, and replaces each with the linear `(c1 - c2) * 1 = 0`, which is cheaper to
+    /// prove and to reason about. Products with a constant side are already linear, and are left
+    /// alone. Constraint indices, and the order wires are allocated in, are unchanged, so raw
+    /// constraints stay valid, but the fingerprint changes. Returns the rewritten circuit and
+    /// each rewrite made.
+    pub fn rewrite_shared_products(&self) -> (Self, Vec<SharedProductRewrite>) {
+        let mut first_with_product: BTreeMap<_, usize> = BTreeMap::new();
+        let mut rewrites = Vec::new();
+        let constraints = self.gadget().constraints.iter().enumerate()
+            .map(|(i, constraint)| {
+                if constant(&constraint.a).is_some() || constant(&constraint.b).is_some()
+                    || is_zero(&constraint.a) || is_zero(&constraint.b) {
+                    return constraint.clone();
+                }
+                let (a, b) = (expression_key(&constraint.a), expression_key(&constraint.b));
+                let key = if a <= b { (a, b) } else { (b, a) };
+                match first_with_product.get(&key) {
+                    Some(&original) => {
+                        rewrites.push(SharedProductRewrite { original, rewritten: i });
+                        let c1 = &self.gadget().constraints[original].c;
+                        Constraint {
+                            a: difference(c1, &constraint.c),
+                            b: Expression::from(&Wire::ONE),
+                            c: Expression::new(BTreeMap::new()),
+                        }
+                    }
+                    None => {
+                        first_with_product.insert(key, i);
+                        constraint.clone()
+                    }
+                }
+            })
+            .collect();
+        let mut circuit = self.with_witness_map(self.witness_map().clone());
+        circuit.gadget = Gadget { constraints, witness_generators: Vec::new() };
+        circuit.preconverted = None;
+        (circuit, rewrites)
+    }
+}
+
+/// A canonical, ordered form of `exp`'s nonzero terms, for detecting equal expressions.
+fn expression_key<F: Field>(exp: &Expression<F>) -> Vec<(u32, BigUint)> {
+    exp.coefficients().iter()
+        .filter(|(_, coeff)| **coeff != Element::zero())
+        .map(|(wire, coeff)| (wire.index, coeff.to_biguint().clone()))
+        .collect()
+}
+
+/// Returns `x - y`, without zero terms.
+fn difference<F: Field>(x: &Expression<F>, y: &Expression<F>) -> Expression<F> {
+    let mut coefficients: BTreeMap<Wire, Element<F>> = x.coefficients().clone();
+    for (wire, coeff) in y.coefficients() {
+        let sum = coefficients.remove(wire).unwrap_or_else(Element::zero) - coeff.clone();
+        coefficients.insert(*wire, sum);
+    }
+    coefficients.retain(|_, coeff| *coeff != Element::zero());
+    Expression::new(coefficients)
+}
+
+/// If `constraint` has one of the shapes `find_equality_classes` recognizes, returns the two wires
+/// it forces equal.
+fn equality<F: Field>(constraint: &Constraint<F>) -> Option<(Wire, Wire)> {
+    let (l, lin) = match (constant(&constraint.a), constant(&constraint.b)) {
+        (Some(l), _) => (l, &constraint.b),
+        (_, Some(l)) => (l, &constraint.a),
+        _ => return None,
+    };
+    // lin * l - c = 0
+    let mut difference: BTreeMap<Wire, Element<F>> = BTreeMap::new();
+    for (wire, coeff) in lin.coefficients() {
+        let term = coeff.clone() * l.clone();
+        let sum = difference.remove(wire).unwrap_or_else(Element::zero) + term;
+        difference.insert(*wire, sum);
+    }
+    for (wire, coeff) in constraint.c.coefficients() {
+        let sum = difference.remove(wire).unwrap_or_else(Element::zero) - coeff.clone();
+        difference.insert(*wire, sum);
+    }
+    difference.retain(|_, coeff| *coeff != Element::zero());
+
+    let terms: Vec<(&Wire, &Element<F>)> = difference.iter().collect();
+    match terms.as_slice() {
+        [(x, p), (y, q)] if **x != Wire::ONE && **y != Wire::ONE
+            && (*p).clone() + (*q).clone() == Element::zero() => Some((**x, **y)),
+        _ => None,
+    }
+}
+
+/// Returns `k` if `exp` is the nonzero constant `k`.
+fn constant<F: Field>(exp: &Expression<F>) -> Option<Element<F>> {
+    let mut terms = exp.coefficients().iter().filter(|(_, coeff)| **coeff != Element::zero());
+    match (terms.next(), terms.next()) {
+        (Some((wire, coeff)), None) if *wire == Wire::ONE => Some(coeff.clone()),
+        _ => None,
+    }
+}
+
+/// Returns whether every coefficient of `exp` is zero.
+fn is_zero<F: Field>(exp: &Expression<F>) -> bool {
+    exp.coefficients().values().all(|coeff| *coeff == Element::zero())
+}
+
+fn find(parent: &BTreeMap<Wire, Wire>, mut wire: Wire) -> Wire {
+    while let Some(next) = parent.get(&wire) {
+        wire = *next;
+    }
+    wire
+}
+
+fn substitute<F: Field>(exp: &Expression<F>, representatives: &BTreeMap<Wire, Wire>) -> Expression<F> {
+    let mut coefficients: BTreeMap<Wire, Element<F>> = BTreeMap::new();
+    for (wire, coeff) in exp.coefficients() {
+        let wire = *representatives.get(wire).unwrap_or(wire);
+        let sum = coefficients.remove(&wire).unwrap_or_else(Element::zero) + coeff.clone();
+        coefficients.insert(wire, sum);
+    }
+    coefficients.retain(|_, coeff| *coeff != Element::zero());
+    Expression::new(coefficients)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use r1cs::{Constraint, Expression, GadgetBuilder, Wire, Bn128 as Bn128F};
+    use rand::thread_rng;
+
+    use crate::{Groth16Prover, SharedProductRewrite, WrappedCircuit};
+    use crate::test_util::{fr, product_circuit, scaled, ProductCircuit};
+
+    #[test]
+    fn product_circuit_reference_counts() {
+        let counts = product_circuit(BTreeMap::new()).wire_reference_counts();
+        let expected: BTreeMap<Wire, usize> = (1..=3).map(|i| (Wire { index: i }, 1)).collect();
+        assert_eq!(counts, expected);
+    }
+
+    #[test]
+    fn conditional_constraints_are_counted() {
+        // x * y = z, and x * y = w when s is nonzero.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let wires = builder.wires(5);
+        let (x, y, z, w, s) = (wires[0], wires[1], wires[2], wires[3], wires[4]);
+        builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+        let constraint = Constraint {
+            a: Expression::from(&x),
+            b: Expression::from(&y),
+            c: Expression::from(&w),
+        };
+        let circuit: ProductCircuit = WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![z])
+            .with_conditional_constraint(constraint, s);
+        let counts = circuit.wire_reference_counts();
+        let expected: BTreeMap<Wire, usize> = vec![(x, 2), (y, 2), (z, 1), (w, 1), (s, 1)]
+            .into_iter()
+            .collect();
+        assert_eq!(counts, expected);
+    }
+
+    #[test]
+    fn product_constraint_couples_x_and_y() {
+        let circuit = product_circuit(BTreeMap::new());
+        let (x, y) = (Wire { index: 1 }, Wire { index: 2 });
+        assert_eq!(circuit.constraints_with_wires(x, y), vec![0]);
+        assert!(circuit.constraints_with_wires(x, Wire { index: 4 }).is_empty());
+    }
+
+    #[test]
+    fn copy_constraints_form_one_class() {
+        // x * 1 = y, 2 y * 1 = 2 w, x * y = z.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let w = builder.wire();
+        let z = builder.wire();
+        let one = Expression::from(&Wire::ONE);
+        let two = r1cs::Element::from(2u8);
+        builder.assert_product(&Expression::from(&x), &one, &Expression::from(&y));
+        builder.assert_product(&scaled(y, two.clone()), &one, &scaled(w, two));
+        builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+        let mut witness_map = BTreeMap::new();
+        for (wire, n) in &[(x, 3u8), (y, 3), (w, 3), (z, 9)] {
+            witness_map.insert(wire.index, fr(*n));
+        }
+        let circuit: ProductCircuit = WrappedCircuit::new(builder.build(), witness_map, vec![z]);
+        assert_eq!(circuit.find_equality_classes(), vec![vec![x, y, w]]);
+
+        let merged = circuit.merge_equal_wires();
+        assert_eq!(merged.gadget().constraints.len(), 1);
+        assert!(merged.find_equality_classes().is_empty());
+        assert_eq!(merged.check_satisfied(), Ok(()));
+        assert_ne!(merged.fingerprint(), circuit.fingerprint());
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&merged, rng).unwrap();
+        let proof = prover.prove(merged.witness_map().clone(), rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(9)]).unwrap());
+    }
+
+    #[test]
+    fn wire_multiplied_by_zero_is_free() {
+        // x * y = z, w * 0 = 0.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let z = builder.wire();
+        let w = builder.wire();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+        builder.assert_product(&Expression::from(&w), &Expression::zero(), &Expression::zero());
+        let circuit: ProductCircuit = WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![z]);
+        assert_eq!(circuit.free_wires(), vec![w]);
+        assert!(product_circuit(BTreeMap::new()).free_wires().is_empty());
+    }
+
+    #[test]
+    fn repeated_product_becomes_linear() {
+        // x * y = z, y * x = w, with z and w public.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let z = builder.wire();
+        let w = builder.wire();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+        builder.assert_product(&Expression::from(&y), &Expression::from(&x), &Expression::from(&w));
+        let circuit: ProductCircuit = WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![z, w]);
+
+        let (rewritten, rewrites) = circuit.rewrite_shared_products();
+        assert_eq!(rewrites, vec![SharedProductRewrite { original: 0, rewritten: 1 }]);
+        assert_eq!(rewritten.gadget().constraints.len(), 2);
+        let linear = &rewritten.gadget().constraints[1];
+        assert_eq!(linear.b.coefficients().keys().collect::<Vec<_>>(), vec![&Wire::ONE]);
+        assert!(rewritten.rewrite_shared_products().1.is_empty());
+        assert_eq!(rewritten.allocation_order(), circuit.allocation_order());
+
+        let witness = |w_value: u8| -> BTreeMap<u32, _> {
+            vec![(x, 2u8), (y, 3), (z, 6), (w, w_value)].into_iter()
+                .map(|(wire, n)| (wire.index, fr(n)))
+                .collect()
+        };
+        for &(w_value, satisfied) in &[(6u8, true), (7, false)] {
+            for circuit in &[&circuit, &rewritten] {
+                let circuit = circuit.with_witness_map(witness(w_value));
+                assert_eq!(circuit.check_satisfied().is_ok(), satisfied);
+            }
+        }
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&rewritten, rng).unwrap();
+        let proof = prover.prove(witness(6), rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(6), fr(6)]).unwrap());
+    }
+}
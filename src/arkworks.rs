@@ -0,0 +1,194 @@
+//! An adapter for synthesizing the same r1cs gadgets into arkworks constraint systems, enabled by
+//! the `arkworks` feature.
+
+use std::collections::{BTreeMap, HashSet};
+use ark_ff::{BigInteger, FpParameters, One, PrimeField as ArkPrimeField};
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystemRef, LinearCombination, SynthesisError, Variable,
+};
+use num::BigUint;
+use pairing::Engine;
+use r1cs::{Element, Expression, Field, Wire};
+
+use crate::{biguint_from_fr, witness_of, Error, FieldConverter, WrappedCircuit};
+
+type Terms<AF> = Vec<(Wire, AF)>;
+
+/// A `WrappedCircuit` converted to arkworks' field `AF`, which can be synthesized into an arkworks
+/// `ConstraintSystemRef`. Wires are allocated in the same order as the bellman synthesis, public
+/// inputs as instance variables and the rest as witness variables, and conditional constraints get
+/// the same two constraint encoding.
+pub struct ArkCircuit<AF: ArkPrimeField> {
+    constraints: Vec<[Terms<AF>; 3]>,
+    conditionals: Vec<([Terms<AF>; 3], Wire, AF)>,
+    witness_map: BTreeMap<u32, AF>,
+    public_inputs: HashSet<Wire>,
+    allocation_order: Vec<Wire>,
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Converts the circuit's constraints and witness into arkworks' field `AF`. Fails if an
+    /// element doesn't fit in `AF`, which can't happen when its modulus is the same as `F`'s.
+    ///
+    /// Panics if the circuit has raw constraints, since they are bellman linear combinations.
+    pub fn to_arkworks<AF: ArkPrimeField>(&self) -> Result<ArkCircuit<AF>, Error> {
+        assert!(self.raw_constraints().is_empty(), "raw constraints can't be converted to arkworks");
+        let terms = |exp: &Expression<F>| -> Result<Terms<AF>, Error> {
+            exp.coefficients().iter()
+                .map(|(wire, coeff)| Ok((*wire, ark_element(coeff)?)))
+                .collect()
+        };
+        let constraints = self.gadget().constraints.iter()
+            .map(|constraint| {
+                Ok([terms(&constraint.a)?, terms(&constraint.b)?, terms(&constraint.c)?])
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let conditionals = self.conditional_constraints().iter()
+            .zip(self.conditional_products())
+            .map(|(conditional, product)| {
+                let constraint = &conditional.constraint;
+                let exps = [terms(&constraint.a)?, terms(&constraint.b)?, terms(&constraint.c)?];
+                Ok((exps, conditional.selector, ark_from_biguint(biguint_from_fr(&product))?))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let witness_map = self.witness_map().iter()
+            .map(|(key, fr)| Ok((*key, ark_from_biguint(biguint_from_fr(fr))?)))
+            .collect::<Result<_, Error>>()?;
+        Ok(ArkCircuit {
+            constraints,
+            conditionals,
+            witness_map,
+            public_inputs: self.public_inputs().iter().cloned().collect(),
+            allocation_order: self.allocation_order(),
+        })
+    }
+}
+
+impl<AF: ArkPrimeField> ArkCircuit<AF> {
+    fn variable(
+        &self,
+        cs: &ConstraintSystemRef<AF>,
+        variable_map: &mut BTreeMap<Wire, Variable>,
+        wire: Wire
+    ) -> Result<Variable, SynthesisError> {
+        if wire == Wire::ONE {
+            return Ok(Variable::One);
+        }
+        if let Some(variable) = variable_map.get(&wire) {
+            return Ok(*variable);
+        }
+        let value = || witness_of(&self.witness_map, wire).cloned()
+            .ok_or(SynthesisError::AssignmentMissing);
+        let variable = if self.public_inputs.contains(&wire) {
+            cs.new_input_variable(value)?
+        } else {
+            cs.new_witness_variable(value)?
+        };
+        variable_map.insert(wire, variable);
+        Ok(variable)
+    }
+
+    fn lc(
+        &self,
+        cs: &ConstraintSystemRef<AF>,
+        variable_map: &mut BTreeMap<Wire, Variable>,
+        terms: &[(Wire, AF)]
+    ) -> Result<LinearCombination<AF>, SynthesisError> {
+        let terms = terms.iter()
+            .map(|(wire, coeff)| Ok((*coeff, self.variable(cs, variable_map, *wire)?)))
+            .collect::<Result<Vec<_>, SynthesisError>>()?;
+        Ok(LinearCombination(terms))
+    }
+}
+
+impl<AF: ArkPrimeField> ConstraintSynthesizer<AF> for ArkCircuit<AF> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<AF>) -> Result<(), SynthesisError> {
+        let mut variable_map = BTreeMap::new();
+        for wire in &self.allocation_order {
+            self.variable(&cs, &mut variable_map, *wire)?;
+        }
+        for [a, b, c] in &self.constraints {
+            let a = self.lc(&cs, &mut variable_map, a)?;
+            let b = self.lc(&cs, &mut variable_map, b)?;
+            let c = self.lc(&cs, &mut variable_map, c)?;
+            cs.enforce_constraint(a, b, c)?;
+        }
+        for ([a, b, c], selector, product) in &self.conditionals {
+            let product = *product;
+            let t = cs.new_witness_variable(|| Ok(product))?;
+            let a = self.lc(&cs, &mut variable_map, a)?;
+            let b = self.lc(&cs, &mut variable_map, b)?;
+            cs.enforce_constraint(a, b, LinearCombination(vec![(AF::one(), t)]))?;
+            let selector = self.variable(&cs, &mut variable_map, *selector)?;
+            let mut difference = self.lc(&cs, &mut variable_map, c)?;
+            for term in difference.0.iter_mut() {
+                term.0 = -term.0;
+            }
+            difference.0.push((AF::one(), t));
+            cs.enforce_constraint(
+                LinearCombination(vec![(AF::one(), selector)]),
+                difference,
+                LinearCombination::zero(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The modulus of arkworks' field `AF`.
+fn ark_modulus<AF: ArkPrimeField>() -> BigUint {
+    BigUint::from_bytes_le(&AF::Params::MODULUS.to_bytes_le())
+}
+
+fn ark_from_biguint<AF: ArkPrimeField>(n: BigUint) -> Result<AF, Error> {
+    let modulus = ark_modulus::<AF>();
+    if n >= modulus {
+        return Err(Error::ElementOutOfRange { value: n, modulus });
+    }
+    Ok(AF::from_le_bytes_mod_order(&n.to_bytes_le()))
+}
+
+fn ark_element<F: Field, AF: ArkPrimeField>(n: &Element<F>) -> Result<AF, Error> {
+    ark_from_biguint(n.to_biguint().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bn254::Fr as ArkFr;
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+    use bellman::Circuit;
+    use pairing::compact_bn256::Bn256;
+
+    use crate::CollectingConstraintSystem;
+    use crate::test_util::{conditional_circuit, conditional_witness, product_circuit, product_witness};
+
+    #[test]
+    fn product_circuit_through_arkworks() {
+        for &(z, satisfied) in &[(6, true), (7, false)] {
+            let circuit = product_circuit(product_witness(2, 3, z));
+            let cs = ConstraintSystem::<ArkFr>::new_ref();
+            circuit.to_arkworks::<ArkFr>().unwrap().generate_constraints(cs.clone()).unwrap();
+            assert_eq!(cs.num_constraints(), circuit.gadget().constraints.len());
+            // The constant one and z.
+            assert_eq!(cs.num_instance_variables(), 2);
+            assert_eq!(cs.num_witness_variables(), 2);
+            assert_eq!(cs.is_satisfied().unwrap(), satisfied);
+        }
+    }
+
+    #[test]
+    fn conditional_circuit_through_arkworks() {
+        // x = 2, y = 3, z = 6, and w must be 4 only when s is nonzero.
+        for &(w, s, satisfied) in &[(4, 1, true), (5, 0, true), (5, 1, false)] {
+            let circuit = conditional_circuit(conditional_witness(2, 3, 6, w, s));
+            let cs = ConstraintSystem::<ArkFr>::new_ref();
+            circuit.to_arkworks::<ArkFr>().unwrap().generate_constraints(cs.clone()).unwrap();
+            let mut bellman_cs = CollectingConstraintSystem::<Bn256>::new();
+            (&circuit).synthesize(&mut bellman_cs).unwrap();
+            assert_eq!(cs.num_constraints(), bellman_cs.constraints.len());
+            assert_eq!(cs.num_instance_variables(), bellman_cs.inputs.len());
+            assert_eq!(cs.num_witness_variables(), bellman_cs.aux.len());
+            assert_eq!(cs.is_satisfied().unwrap(), satisfied);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+#[cfg(feature = "prover")]
+use std::collections::BTreeMap;
+#[cfg(feature = "prover")]
+use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(feature = "prover")]
+use bellman::groth16::Proof;
+#[cfg(feature = "prover")]
+use pairing::Engine;
+#[cfg(feature = "prover")]
+use r1cs::Field;
+#[cfg(feature = "prover")]
+use rand::Rng;
+use serde_json::json;
+#[cfg(feature = "prover")]
+use sha2::{Digest, Sha256};
+
+use crate::Fingerprint;
+#[cfg(feature = "prover")]
+use crate::{serialize_public_inputs, Error, FieldConverter, Groth16Prover};
+
+/// A record of one proving operation, for audit logs. It identifies the statement proven and the
+/// proof produced, but holds nothing derived from the private witness.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// When the proof was created, in seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub fingerprint: Fingerprint,
+    /// The public inputs, in the canonical layout of `serialize_public_inputs`.
+    pub public_inputs: Vec<u8>,
+    /// The SHA-256 digest of the proof, as serialized by `Proof::write`.
+    pub proof_hash: [u8; 32],
+}
+
+impl AuditRecord {
+    /// Serializes the record as a JSON object, with byte strings in lowercase hex.
+    pub fn to_json(&self) -> String {
+        json!({
+            "timestamp": self.timestamp,
+            "fingerprint": to_hex(&self.fingerprint),
+            "public_inputs": to_hex(&self.public_inputs),
+            "proof_hash": to_hex(&self.proof_hash),
+        }).to_string()
+    }
+}
+
+#[cfg(feature = "prover")]
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> Groth16Prover<F, E, C> {
+    /// Like `prove`, but also returns an audit record of the operation.
+    pub fn prove_with_audit<R: Rng>(
+        &self,
+        witness_map: BTreeMap<u32, E::Fr>,
+        rng: &mut R
+    ) -> Result<(Proof<E>, AuditRecord), Error> {
+        let circuit = self.circuit(witness_map);
+        let public_inputs = serialize_public_inputs(&circuit.public_input_values());
+        let proof = self.prove_circuit(circuit, rng)?;
+        let mut proof_bytes = vec![];
+        proof.write(&mut proof_bytes).expect("writing to a Vec can't fail");
+        let mut proof_hash = [0u8; 32];
+        proof_hash.copy_from_slice(&Sha256::digest(&proof_bytes));
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let record = AuditRecord { timestamp, fingerprint: self.fingerprint(), public_inputs, proof_hash };
+        Ok((proof, record))
+    }
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use serde_json::Value;
+    use sha2::{Digest, Sha256};
+    use rand::thread_rng;
+
+    use crate::{serialize_public_inputs, Groth16Prover};
+    use crate::audit::to_hex;
+    use crate::test_util::{fr, product_circuit, product_witness};
+
+    #[test]
+    fn audit_record_describes_proof() {
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&product_circuit(BTreeMap::new()), rng).unwrap();
+        let (proof, record) = prover.prove_with_audit(product_witness(2, 3, 6), rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(6)]).unwrap());
+
+        let mut proof_bytes = vec![];
+        proof.write(&mut proof_bytes).unwrap();
+        assert_eq!(record.fingerprint, prover.fingerprint());
+        assert_eq!(record.public_inputs, serialize_public_inputs(&[fr(6)]));
+        assert_eq!(&record.proof_hash[..], &Sha256::digest(&proof_bytes)[..]);
+        assert!(record.timestamp > 0);
+
+        // Only these fields are recorded; the private x and y never appear.
+        let json: Value = serde_json::from_str(&record.to_json()).unwrap();
+        let mut keys: Vec<&String> = json.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["fingerprint", "proof_hash", "public_inputs", "timestamp"]);
+        assert_eq!(json["public_inputs"], Value::String(to_hex(&record.public_inputs)));
+        assert_eq!(json["timestamp"], Value::from(record.timestamp));
+    }
+}
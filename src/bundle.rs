@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+use bellman::groth16::{prepare_verifying_key, verify_proof, Proof, VerifyingKey};
+use pairing::Engine;
+
+use crate::{read_public_inputs, write_public_inputs, CachedVerifier, Error, Fingerprint};
+
+/// A proof in one serializable value with the public inputs it was made for, the verifying key,
+/// and the fingerprint and metadata of the circuit.
+///
+/// The bundled verifying key is only a claim by whoever made the bundle, since anyone can run a
+/// setup for a statement of their choosing. A verifier must obtain the key it trusts separately;
+/// `verify` checks the bundled one against it.
+pub struct VerificationBundle<E: Engine> {
+    pub fingerprint: Fingerprint,
+    pub metadata: BTreeMap<String, String>,
+    pub vk: VerifyingKey<E>,
+    pub public_inputs: Vec<E::Fr>,
+    pub proof: Proof<E>,
+}
+
+impl<E: Engine> VerificationBundle<E> {
+    /// Verifies the bundled proof against the bundled public inputs, with `trusted_vk`, a key the
+    /// verifier authenticated by its own means. Fails with `VerifyingKeyMismatch` if the bundled
+    /// key isn't `trusted_vk`.
+    pub fn verify(&self, trusted_vk: &VerifyingKey<E>) -> Result<bool, Error> {
+        if self.vk != *trusted_vk {
+            return Err(Error::VerifyingKeyMismatch);
+        }
+        let pvk = prepare_verifying_key(trusted_vk);
+        Ok(verify_proof(&pvk, &self.proof, &self.public_inputs)?)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.fingerprint)?;
+        writer.write_all(&(self.metadata.len() as u32).to_le_bytes())?;
+        for (key, value) in &self.metadata {
+            write_string(key, &mut writer)?;
+            write_string(value, &mut writer)?;
+        }
+        self.vk.write(&mut writer)?;
+        write_public_inputs(&self.public_inputs, &mut writer)?;
+        self.proof.write(&mut writer)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut fingerprint = [0u8; 32];
+        reader.read_exact(&mut fingerprint)?;
+        let mut metadata = BTreeMap::new();
+        for _ in 0..read_u32(&mut reader)? {
+            let key = read_string(&mut reader)?;
+            let value = read_string(&mut reader)?;
+            metadata.insert(key, value);
+        }
+        let vk = VerifyingKey::read(&mut reader)?;
+        let public_inputs = read_public_inputs(&mut reader)?;
+        let proof = Proof::read(&mut reader)?;
+        Ok(Self { fingerprint, metadata, vk, public_inputs, proof })
+    }
+}
+
+/// A verifying key on its own, for verifier-only deployments which shouldn't load the proving
+/// parameters. It records the circuit's fingerprint and how many public inputs proofs have.
+pub struct VerifyingKeyBundle<E: Engine> {
+    pub fingerprint: Fingerprint,
+    pub num_public_inputs: usize,
+    pub vk: VerifyingKey<E>,
+}
+
+impl<E: Engine> VerifyingKeyBundle<E> {
+    /// Prepares the verifying key, for verifying any number of proofs.
+    pub fn verifier(&self) -> CachedVerifier<E> {
+        CachedVerifier::new(&self.vk)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.fingerprint)?;
+        writer.write_all(&(self.num_public_inputs as u32).to_le_bytes())?;
+        self.vk.write(&mut writer)
+    }
+
+    /// Reads a bundle written by `write`, checking that the verifying key has one input query term
+    /// per public input, plus one for the constant one.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut fingerprint = [0u8; 32];
+        reader.read_exact(&mut fingerprint)?;
+        let num_public_inputs = read_u32(&mut reader)? as usize;
+        let vk = VerifyingKey::read(&mut reader)?;
+        if vk.ic.len() != num_public_inputs + 1 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                "the verifying key doesn't match the public input count"));
+        }
+        Ok(Self { fingerprint, num_public_inputs, vk })
+    }
+}
+
+fn write_string<W: Write>(s: &str, mut writer: W) -> io::Result<()> {
+    writer.write_all(&(s.len() as u32).to_le_bytes())?;
+    writer.write_all(s.as_bytes())
+}
+
+/// The longest metadata string `VerificationBundle::read` accepts, so that a corrupt length can't
+/// force a huge allocation.
+const MAX_STRING_LENGTH: u32 = 1 << 16;
+
+fn read_string<R: Read>(mut reader: R) -> io::Result<String> {
+    let len = read_u32(&mut reader)?;
+    if len > MAX_STRING_LENGTH {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "metadata string too long"));
+    }
+    let mut bytes = Vec::new();
+    reader.take(u64::from(len)).read_to_end(&mut bytes)?;
+    if bytes.len() != len as usize {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated metadata string"));
+    }
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn read_u32<R: Read>(mut reader: R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::io;
+    use pairing::compact_bn256::Bn256;
+    use rand::thread_rng;
+
+    use crate::{Groth16Prover, VerificationBundle, VerifyingKeyBundle};
+    use crate::test_util::{fr, product_circuit, product_witness};
+
+    #[test]
+    fn verify_with_only_the_verifying_key() {
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&product_circuit(BTreeMap::new()), rng).unwrap();
+        let mut bytes = vec![];
+        prover.verifying_key_bundle().write(&mut bytes).unwrap();
+        let proof = prover.prove(product_witness(2, 3, 6), rng).unwrap();
+        drop(prover);
+
+        let bundle = VerifyingKeyBundle::<Bn256>::read(&bytes[..]).unwrap();
+        assert_eq!(bundle.fingerprint, product_circuit(BTreeMap::new()).fingerprint());
+        assert_eq!(bundle.num_public_inputs, 1);
+        let verifier = bundle.verifier();
+        assert!(verifier.verify(&proof, &[fr(6)]).unwrap());
+        assert!(!verifier.verify(&proof, &[fr(7)]).unwrap());
+
+        // A count which disagrees with the key is rejected.
+        bytes[32] = 2;
+        assert!(VerifyingKeyBundle::<Bn256>::read(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn metadata_lengths_are_checked() {
+        // A fingerprint, one metadata entry, and the length of its key.
+        let header = |key_length: u32| {
+            let mut bytes = vec![0u8; 32];
+            bytes.extend_from_slice(&1u32.to_le_bytes());
+            bytes.extend_from_slice(&key_length.to_le_bytes());
+            bytes
+        };
+        let err = VerificationBundle::<Bn256>::read(&header(u32::MAX)[..]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let mut truncated = header(5);
+        truncated.extend_from_slice(b"nam");
+        let err = VerificationBundle::<Bn256>::read(&truncated[..]).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,134 @@
+use std::marker::PhantomData;
+use pairing::Engine;
+use r1cs::{Element, Field, Wire};
+
+use crate::{biguint_from_fr, moduli_match, Error, FieldConverter, WrappedCircuit};
+
+/// A converter for development which delegates to `C`, then converts each result back to an
+/// integer and checks that it is the one it started from, to catch repr or Montgomery form bugs.
+/// This only makes sense when `F` and `E::Fr` are the same field.
+///
+/// `convert_field` panics on a mismatch; `try_convert` returns an error instead.
+pub struct CheckedConverter<C> {
+    _c: PhantomData<C>,
+}
+
+impl<C> CheckedConverter<C> {
+    pub fn try_convert<F: Field, E: Engine>(n: &Element<F>) -> Result<E::Fr, Error>
+        where C: FieldConverter<F, E>
+    {
+        check_round_trip::<F, E, C>(n, None)
+    }
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> FieldConverter<F, E> for CheckedConverter<C> {
+    fn convert_field(n: &Element<F>) -> E::Fr {
+        Self::try_convert::<F, E>(n).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn is_compatible() -> bool {
+        C::is_compatible() && moduli_match::<F, E>()
+    }
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Converts every coefficient in the circuit's constraints with `C`, and checks that each
+    /// converts back to the integer it started from, as `CheckedConverter` does. The error
+    /// identifies the first coefficient which doesn't, with conditional constraints numbered after
+    /// the gadget's constraints, as in `check_satisfied`.
+    pub fn check_conversions(&self) -> Result<(), Error> {
+        let conditionals = self.conditional_constraints().iter().map(|conditional| &conditional.constraint);
+        for (i, constraint) in self.gadget().constraints.iter().chain(conditionals).enumerate() {
+            for exp in &[&constraint.a, &constraint.b, &constraint.c] {
+                for (wire, coeff) in exp.coefficients() {
+                    check_round_trip::<F, E, C>(coeff, Some((i, *wire)))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_round_trip<F: Field, E: Engine, C: FieldConverter<F, E>>(
+    n: &Element<F>,
+    location: Option<(usize, Wire)>
+) -> Result<E::Fr, Error> {
+    let fr = C::convert_field(n);
+    let converted = biguint_from_fr(&fr);
+    if &converted == n.to_biguint() {
+        Ok(fr)
+    } else {
+        Err(Error::ConversionCorruption { location, value: n.to_biguint().clone(), converted })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use num::BigUint;
+    use pairing::compact_bn256::{Bn256, Fr};
+    use pairing::ff::Field as _;
+    use r1cs::{Constraint, Element, Expression, GadgetBuilder, Wire, Bn128 as Bn128F};
+
+    use crate::{Bn256Converter, CheckedConverter, Error, FieldConverter, WrappedCircuit};
+    use crate::test_util::{product_circuit, scaled};
+
+    /// Gets every conversion wrong, except for zero.
+    struct BrokenConverter;
+
+    impl FieldConverter<Bn128F, Bn256> for BrokenConverter {
+        fn convert_field(n: &Element<Bn128F>) -> Fr {
+            let mut fr = Bn256Converter::convert_field(n);
+            fr.double();
+            fr
+        }
+    }
+
+    #[test]
+    fn round_trips_are_checked() {
+        let n = Element::<Bn128F>::from(6u8);
+        assert_eq!(CheckedConverter::<Bn256Converter>::try_convert::<Bn128F, Bn256>(&n).unwrap(),
+            Bn256Converter::convert_field(&n));
+        assert!(CheckedConverter::<BrokenConverter>::try_convert::<Bn128F, Bn256>(&n).is_err());
+        assert!(product_circuit(BTreeMap::new()).check_conversions().is_ok());
+
+        // x * 1 = 3 z. The first coefficient checked, x's, is already converted wrongly.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let z = builder.wire();
+        let three_z = scaled(z, Element::from(3u8));
+        builder.assert_product(&Expression::from(&x), &Expression::from(&Wire::ONE), &three_z);
+        let circuit: WrappedCircuit<Bn128F, Bn256, BrokenConverter> =
+            WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![z]);
+        match circuit.check_conversions() {
+            Err(Error::ConversionCorruption { location, value, converted }) => {
+                assert_eq!(location, Some((0, x)));
+                assert_eq!(value, BigUint::from(1u8));
+                assert_eq!(converted, BigUint::from(2u8));
+            }
+            _ => panic!("expected a conversion corruption"),
+        }
+    }
+
+    #[test]
+    fn conditional_constraints_are_checked() {
+        // 0 * 0 = 0, and x * 1 = z when s is nonzero.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let z = builder.wire();
+        let s = builder.wire();
+        builder.assert_product(&Expression::zero(), &Expression::zero(), &Expression::zero());
+        let constraint = Constraint {
+            a: Expression::from(&x),
+            b: Expression::from(&Wire::ONE),
+            c: Expression::from(&z),
+        };
+        let circuit: WrappedCircuit<Bn128F, Bn256, BrokenConverter> =
+            WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![z])
+                .with_conditional_constraint(constraint, s);
+        match circuit.check_conversions() {
+            Err(Error::ConversionCorruption { location, .. }) => assert_eq!(location, Some((1, x))),
+            _ => panic!("expected a conversion corruption in the conditional constraint"),
+        }
+    }
+}
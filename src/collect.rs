@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use bellman::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+use blake2s_simd::Params;
+use pairing::Engine;
+use pairing::ff::{Field as _, PrimeField, PrimeFieldRepr};
+use r1cs::Field;
+
+use crate::{FieldConverter, R1csMatrices, WrappedCircuit};
+use crate::audit::to_hex;
+
+/// A constraint system which simply records everything synthesized into it: variable assignments
+/// and constraints, exactly as bellman's own constraint systems receive them. Useful for checking
+/// what a circuit enforces without running a setup.
+pub struct CollectingConstraintSystem<E: Engine> {
+    /// The values of the input variables. Input 0 is the constant one.
+    pub inputs: Vec<E::Fr>,
+    /// The values of the auxiliary variables.
+    pub aux: Vec<E::Fr>,
+    /// The enforced constraints, as `(a, b, c)` for `a * b = c`.
+    pub constraints: Vec<(LinearCombination<E>, LinearCombination<E>, LinearCombination<E>)>,
+}
+
+impl<E: Engine> CollectingConstraintSystem<E> {
+    pub fn new() -> Self {
+        Self { inputs: vec![E::Fr::one()], aux: Vec::new(), constraints: Vec::new() }
+    }
+
+    /// Returns the recorded constraints as matrices, with the inputs' columns followed by the
+    /// auxiliary variables' columns.
+    pub fn matrices(&self) -> R1csMatrices<E::Fr> {
+        let num_inputs = self.inputs.len();
+        let row = |lc: &LinearCombination<E>| {
+            let mut row = BTreeMap::new();
+            for (variable, coeff) in lc.as_ref() {
+                let column = match variable.get_unchecked() {
+                    Index::Input(i) => i,
+                    Index::Aux(i) => num_inputs + i,
+                };
+                row.entry(column).or_insert_with(E::Fr::zero).add_assign(coeff);
+            }
+            row.retain(|_, coeff: &mut E::Fr| !coeff.is_zero());
+            row
+        };
+        let mut matrices = R1csMatrices::new(num_inputs, self.aux.len());
+        for (a, b, c) in &self.constraints {
+            matrices.a.push(row(a));
+            matrices.b.push(row(b));
+            matrices.c.push(row(c));
+        }
+        matrices
+    }
+
+    /// Digests the recorded constraint system with the encoding of the `TestConstraintSystem::hash`
+    /// in bellman's gadget test utilities, so the two can be compared directly. The digest covers
+    /// the variable and constraint counts and the constraints, with each linear combination's terms
+    /// merged, stripped of zeros and ordered inputs first, but not the assignments or annotations.
+    pub fn hash(&self) -> String {
+        let mut hasher = Params::new().hash_length(32).to_state();
+        hasher.update(&(self.inputs.len() as u64).to_be_bytes());
+        hasher.update(&(self.aux.len() as u64).to_be_bytes());
+        hasher.update(&(self.constraints.len() as u64).to_be_bytes());
+        let matrices = self.matrices();
+        let num_inputs = self.inputs.len();
+        for row in matrices.a.iter().zip(&matrices.b).zip(&matrices.c)
+            .flat_map(|((a, b), c)| vec![a, b, c]) {
+            hasher.update(&(row.len() as u64).to_be_bytes());
+            for (column, coeff) in row {
+                let (kind, index) = if *column < num_inputs {
+                    (b'I', *column)
+                } else {
+                    (b'A', column - num_inputs)
+                };
+                hasher.update(&[kind]);
+                hasher.update(&(index as u64).to_be_bytes());
+                let mut coeff_bytes = Vec::new();
+                coeff.into_repr().write_be(&mut coeff_bytes).unwrap();
+                hasher.update(&coeff_bytes);
+            }
+        }
+        to_hex(hasher.finalize().as_bytes())
+    }
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Synthesizes the circuit and returns the `TestConstraintSystem`-style hash of the converted
+    /// constraint system, a stable digest for asserting that conversion output is unchanged across
+    /// versions of this crate. Unlike `fingerprint`, it depends on the conversion and on the order
+    /// variables are allocated in.
+    pub fn constraint_system_hash(&self) -> Result<String, SynthesisError> {
+        let mut cs = CollectingConstraintSystem::<E>::new();
+        self.synthesize(&mut cs)?;
+        Ok(cs.hash())
+    }
+}
+
+impl<E: Engine> Default for CollectingConstraintSystem<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Engine> ConstraintSystem<E> for CollectingConstraintSystem<E> {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(&mut self, _annotation: A, f: F) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        self.aux.push(f()?);
+        Ok(Variable::new_unchecked(Index::Aux(self.aux.len() - 1)))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _annotation: A, f: F) -> Result<Variable, SynthesisError>
+        where F: FnOnce() -> Result<E::Fr, SynthesisError>, A: FnOnce() -> AR, AR: Into<String>
+    {
+        self.inputs.push(f()?);
+        Ok(Variable::new_unchecked(Index::Input(self.inputs.len() - 1)))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _annotation: A, a: LA, b: LB, c: LC)
+        where A: FnOnce() -> AR, AR: Into<String>,
+              LA: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LB: FnOnce(LinearCombination<E>) -> LinearCombination<E>,
+              LC: FnOnce(LinearCombination<E>) -> LinearCombination<E>
+    {
+        let a = a(LinearCombination::zero());
+        let b = b(LinearCombination::zero());
+        let c = c(LinearCombination::zero());
+        self.constraints.push((a, b, c));
+    }
+
+    fn push_namespace<NR, N>(&mut self, _name_fn: N) where NR: Into<String>, N: FnOnce() -> NR {}
+
+    fn pop_namespace(&mut self) {}
+
+    fn get_root(&mut self) -> &mut Self::Root {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use bellman::{Circuit, Index, LinearCombination, Variable};
+    use blake2s_simd::Params;
+    use pairing::compact_bn256::{Bn256, Fr};
+    use pairing::ff::{Field as _, PrimeField, PrimeFieldRepr};
+    use r1cs::Wire;
+
+    use crate::{one_variable, CollectingConstraintSystem, RawConstraint};
+    use crate::audit::to_hex;
+    use crate::test_util::{
+        conditional_circuit, conditional_witness, fr, product_circuit, product_witness, ProductCircuit,
+    };
+
+    /// `TestConstraintSystem::hash`, ported line for line from bellman's gadget test utilities,
+    /// over the linear combinations exactly as they were enforced.
+    fn reference_hash(cs: &CollectingConstraintSystem<Bn256>) -> String {
+        fn proc_lc(terms: &[(Variable, Fr)]) -> BTreeMap<(u8, usize), Fr> {
+            let mut map = BTreeMap::new();
+            for (var, coeff) in terms {
+                let key = match var.get_unchecked() {
+                    Index::Input(i) => (0, i),
+                    Index::Aux(i) => (1, i),
+                };
+                map.entry(key).or_insert_with(Fr::zero).add_assign(coeff);
+            }
+            map.retain(|_, coeff| !coeff.is_zero());
+            map
+        }
+        fn hash_lc(terms: &[(Variable, Fr)], h: &mut blake2s_simd::State) {
+            let map = proc_lc(terms);
+            h.update(&(map.len() as u64).to_be_bytes());
+            for ((kind, index), coeff) in map {
+                let mut buf = vec![if kind == 0 { b'I' } else { b'A' }];
+                buf.extend_from_slice(&(index as u64).to_be_bytes());
+                coeff.into_repr().write_be(&mut buf).unwrap();
+                h.update(&buf);
+            }
+        }
+        let mut h = Params::new().hash_length(32).to_state();
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&(cs.inputs.len() as u64).to_be_bytes());
+        buf.extend_from_slice(&(cs.aux.len() as u64).to_be_bytes());
+        buf.extend_from_slice(&(cs.constraints.len() as u64).to_be_bytes());
+        h.update(&buf);
+        for (a, b, c) in &cs.constraints {
+            hash_lc(a.as_ref(), &mut h);
+            hash_lc(b.as_ref(), &mut h);
+            hash_lc(c.as_ref(), &mut h);
+        }
+        to_hex(h.finalize().as_bytes())
+    }
+
+    fn assert_matches_reference(circuit: &ProductCircuit) {
+        let mut cs = CollectingConstraintSystem::<Bn256>::new();
+        circuit.synthesize(&mut cs).unwrap();
+        assert_eq!(cs.hash(), reference_hash(&cs));
+    }
+
+    #[test]
+    fn hash_matches_test_constraint_system() {
+        assert_matches_reference(&product_circuit(product_witness(2, 3, 6)));
+
+        let mut circuit = conditional_circuit(conditional_witness(2, 3, 6, 4, 1));
+        assert_matches_reference(&circuit);
+        // A raw constraint with a repeated and a cancelling term, (x + x) * 1 = 2 x + y - y.
+        let x = circuit.variable_of(Wire { index: 1 }).unwrap();
+        let y = circuit.variable_of(Wire { index: 2 }).unwrap();
+        let mut minus_one = Fr::one();
+        minus_one.negate();
+        circuit.add_raw_constraint(RawConstraint {
+            a: LinearCombination::zero() + (Fr::one(), x) + (Fr::one(), x),
+            b: LinearCombination::zero() + (Fr::one(), one_variable()),
+            c: LinearCombination::zero() + (fr(2), x) + (Fr::one(), y) + (minus_one, y),
+        });
+        assert_matches_reference(&circuit);
+    }
+
+    #[test]
+    fn constraint_system_hash_is_stable() {
+        let circuit = product_circuit(BTreeMap::new());
+        let hash = circuit.constraint_system_hash().unwrap();
+        assert_eq!(hash.len(), 64);
+        assert_eq!(circuit.constraint_system_hash().unwrap(), hash);
+        // The hash covers the structure, not the witness.
+        assert_eq!(product_circuit(product_witness(2, 3, 6)).constraint_system_hash().unwrap(), hash);
+        let (with_context, _) = circuit.with_context_input();
+        assert_ne!(with_context.constraint_system_hash().unwrap(), hash);
+    }
+}
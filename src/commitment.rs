@@ -0,0 +1,331 @@
+//! Support for splitting a circuit's public inputs into revealed and committed ones.
+//!
+//! Revealed inputs stay in the Groth16 public input vector. Committed inputs are bound through a
+//! separate commitment computed inside the circuit: `with_committed_inputs` makes them private
+//! wires, and adds a public input constrained to be a hash of them and a private blinder. A proof
+//! then shows that the prover knows committed values which hash to that commitment, and which
+//! satisfy the rest of the circuit.
+//!
+//! The verifier receives a `CommittedStatement`, the revealed values and the commitment, and
+//! verifies with those alone using `verify_committed`; it never learns the committed values. A
+//! party which is later given an opening, the committed values and the blinder, checks it by
+//! recomputing the commitment with `commit_inputs`.
+//!
+//! The hash is MiMC-7 in Miyaguchi-Preneel mode over the circuit's field, which costs 365
+//! constraints per committed value, plus 365 for the blinder. It hides the committed values as
+//! long as the blinder is random, and is kept secret until the commitment is opened.
+
+use std::collections::{BTreeMap, BTreeSet};
+use bellman::SynthesisError;
+use bellman::groth16::{prepare_verifying_key, verify_proof, Proof, VerifyingKey};
+use num::{BigUint, Zero};
+use pairing::Engine;
+use pairing::ff::Field as _;
+use r1cs::{Constraint, Element, Expression, Field, Wire};
+use sha2::{Digest, Sha256};
+
+use crate::{wire_for_key, witness_key, FieldConverter, WrappedCircuit};
+
+/// The number of MiMC-7 rounds, enough for 254-bit fields.
+const ROUNDS: usize = 91;
+
+/// The wires each hashed value takes: four for each round, and one for the chaining value.
+const WIRES_PER_BLOCK: u32 = 4 * ROUNDS as u32 + 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PublicInputKind {
+    /// The input's value is given to the verifier, as part of the Groth16 public input vector.
+    Revealed,
+    /// The input is private to the prover, and bound by the commitment `with_committed_inputs`
+    /// adds.
+    Committed,
+}
+
+/// Assigns a `PublicInputKind` to each public input of a circuit. Inputs which were not marked
+/// are revealed.
+#[derive(Clone, Debug, Default)]
+pub struct PublicInputKinds {
+    committed: BTreeSet<Wire>,
+}
+
+impl PublicInputKinds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mark(&mut self, wire: Wire, kind: PublicInputKind) {
+        match kind {
+            PublicInputKind::Revealed => self.committed.remove(&wire),
+            PublicInputKind::Committed => self.committed.insert(wire),
+        };
+    }
+
+    pub fn kind(&self, wire: Wire) -> PublicInputKind {
+        if self.committed.contains(&wire) {
+            PublicInputKind::Committed
+        } else {
+            PublicInputKind::Revealed
+        }
+    }
+}
+
+/// The wires `with_committed_inputs` adds to a circuit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InputCommitment {
+    /// The committed wires, now private, in the order they are hashed.
+    pub committed: Vec<Wire>,
+    /// The private wire whose value blinds the commitment.
+    pub blinder: Wire,
+    /// The public wire carrying the commitment. It is the last public input.
+    pub commitment: Wire,
+}
+
+impl InputCommitment {
+    /// Returns the wire of each intermediate value of the hash, in the order they are computed,
+    /// ending with the commitment.
+    fn intermediate_wires(&self) -> impl Iterator<Item = Wire> {
+        (self.blinder.index + 1..=self.commitment.index).map(wire_for_key)
+    }
+}
+
+/// The public statement of a proof whose public inputs are partly committed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommittedStatement<E: Engine> {
+    /// The values of the revealed public inputs, in allocation order.
+    pub revealed: Vec<E::Fr>,
+    /// The commitment to the values of the committed public inputs.
+    pub commitment: E::Fr,
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Makes the public inputs marked `Committed` in `kinds` private, and adds a public input, the
+    /// last, constrained to be the commitment to them. The added wires are above every wire the
+    /// circuit uses. The witness of the new wires is computed by `with_commitment_witness`.
+    ///
+    /// Panics if 7 divides the order of `F` minus one, in which case MiMC-7 isn't a permutation.
+    pub fn with_committed_inputs(mut self, kinds: &PublicInputKinds) -> (Self, InputCommitment) {
+        let p_minus_one = F::order() - BigUint::from(1u32);
+        assert!(!(p_minus_one % BigUint::from(7u32)).is_zero(),
+                "MiMC-7 needs 7 not to divide p - 1");
+        let committed: Vec<Wire> = self.public_input_order().into_iter()
+            .filter(|wire| kinds.kind(*wire) == PublicInputKind::Committed)
+            .collect();
+        let max = self.referenced_wires().into_iter()
+            .chain(self.public_inputs().iter().cloned())
+            .chain(self.witness_map().keys().map(|key| wire_for_key(*key)))
+            .max()
+            .unwrap_or(Wire::ONE);
+        let blinder = wire_for_key(max.index + 1);
+        let blocks = committed.len() as u32 + 1;
+        let commitment = InputCommitment {
+            commitment: wire_for_key(blinder.index + blocks * WIRES_PER_BLOCK),
+            committed,
+            blinder,
+        };
+
+        let constants: Vec<Element<F>> = round_constants();
+        let mut intermediates = commitment.intermediate_wires();
+        let mut key = Expression::zero();
+        for message in Some(&commitment.blinder).into_iter().chain(&commitment.committed) {
+            let message = Expression::from(message);
+            let mut state = message.clone();
+            for constant in &constants {
+                let sum = state + key.clone() + constant_expression(constant.clone());
+                let square = Expression::from(&intermediates.next().unwrap());
+                let fourth = Expression::from(&intermediates.next().unwrap());
+                let sixth = Expression::from(&intermediates.next().unwrap());
+                state = Expression::from(&intermediates.next().unwrap());
+                for (a, b, c) in vec![
+                    (&sum, &sum, &square),
+                    (&square, &square, &fourth),
+                    (&fourth, &square, &sixth),
+                    (&sixth, &sum, &state),
+                ] {
+                    let constraint = Constraint { a: a.clone(), b: b.clone(), c: c.clone() };
+                    self.gadget.constraints.push(constraint);
+                }
+            }
+            // The chaining value key + message + E_key(message), where E_key(message) is the
+            // final state plus the key.
+            let chained = Expression::from(&intermediates.next().unwrap());
+            self.gadget.constraints.push(Constraint {
+                a: key.clone() + key + message + state,
+                b: Expression::from(&Wire::ONE),
+                c: chained.clone(),
+            });
+            key = chained;
+        }
+
+        self.preconverted = None;
+        self.public_inputs.retain(|wire| kinds.kind(*wire) == PublicInputKind::Revealed);
+        self.public_inputs.push(commitment.commitment);
+        (self, commitment)
+    }
+
+    /// Returns the circuit's witness map extended with the witness of the wires
+    /// `with_committed_inputs` added, for the given blinder. The committed wires' values are
+    /// taken from the witness map.
+    pub fn with_commitment_witness(
+        &self,
+        commitment: &InputCommitment,
+        blinder: E::Fr
+    ) -> BTreeMap<u32, E::Fr> {
+        let values: Vec<E::Fr> = commitment.committed.iter()
+            .map(|wire| self.wire_value(*wire))
+            .collect();
+        let mut witness_map = self.witness_map().clone();
+        witness_map.insert(witness_key(commitment.blinder), blinder);
+        let intermediates = hash_intermediates::<F, E, C>(&values, blinder);
+        for (wire, value) in commitment.intermediate_wires().zip(intermediates) {
+            witness_map.insert(witness_key(wire), value);
+        }
+        witness_map
+    }
+
+    /// Computes the commitment to `values`, the committed inputs in the order
+    /// `InputCommitment::committed` lists them, under `blinder`. This is how an opening is checked.
+    pub fn commit_inputs(values: &[E::Fr], blinder: E::Fr) -> E::Fr {
+        *hash_intermediates::<F, E, C>(values, blinder).last().unwrap()
+    }
+
+    /// Returns the statement a verifier needs: the values of the revealed public inputs, and the
+    /// commitment, as assigned by the witness map.
+    pub fn committed_statement(&self, commitment: &InputCommitment) -> CommittedStatement<E> {
+        let revealed = self.public_input_order().into_iter()
+            .filter(|wire| *wire != commitment.commitment)
+            .map(|wire| self.wire_value(wire))
+            .collect();
+        CommittedStatement { revealed, commitment: self.wire_value(commitment.commitment) }
+    }
+}
+
+/// Verifies a proof of a circuit built by `with_committed_inputs` against a statement, with only
+/// the revealed inputs and the commitment as public inputs.
+pub fn verify_committed<E: Engine>(
+    vk: &VerifyingKey<E>,
+    proof: &Proof<E>,
+    statement: &CommittedStatement<E>
+) -> Result<bool, SynthesisError> {
+    let mut public_inputs = statement.revealed.clone();
+    public_inputs.push(statement.commitment);
+    verify_proof(&prepare_verifying_key(vk), proof, &public_inputs)
+}
+
+/// The MiMC-7 round constants: zero, and then SHA-256 digests of a fixed label and the round
+/// number, reduced modulo the field's order.
+fn round_constants<F: Field>() -> Vec<Element<F>> {
+    (0..ROUNDS)
+        .map(|i| match i {
+            0 => Element::zero(),
+            _ => {
+                let digest = Sha256::digest(format!("r1cs-bellman mimc7 {}", i).as_bytes());
+                Element::from(BigUint::from_bytes_le(&digest) % F::order())
+            }
+        })
+        .collect()
+}
+
+fn constant_expression<F: Field>(constant: Element<F>) -> Expression<F> {
+    let mut coefficients = BTreeMap::new();
+    coefficients.insert(Wire::ONE, constant);
+    Expression::new(coefficients)
+}
+
+/// Hashes the blinder followed by `values`, returning every intermediate value, in the order of
+/// `InputCommitment::intermediate_wires`.
+fn hash_intermediates<F: Field, E: Engine, C: FieldConverter<F, E>>(
+    values: &[E::Fr],
+    blinder: E::Fr
+) -> Vec<E::Fr> {
+    let constants: Vec<E::Fr> = round_constants::<F>().iter().map(C::convert_field).collect();
+    let mut intermediates = Vec::with_capacity((values.len() + 1) * WIRES_PER_BLOCK as usize);
+    let mut key = E::Fr::zero();
+    for message in Some(&blinder).into_iter().chain(values) {
+        let mut state = *message;
+        for constant in &constants {
+            let mut sum = state;
+            sum.add_assign(&key);
+            sum.add_assign(constant);
+            let mut square = sum;
+            square.square();
+            let mut fourth = square;
+            fourth.square();
+            let mut sixth = fourth;
+            sixth.mul_assign(&square);
+            state = sixth;
+            state.mul_assign(&sum);
+            intermediates.extend_from_slice(&[square, fourth, sixth, state]);
+        }
+        let mut chained = key;
+        chained.double();
+        chained.add_assign(message);
+        chained.add_assign(&state);
+        intermediates.push(chained);
+        key = chained;
+    }
+    intermediates
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use r1cs::{Expression, GadgetBuilder, Wire, Bn128 as Bn128F};
+    use rand::thread_rng;
+
+    use crate::{CommittedStatement, Groth16Prover, PublicInputKind, PublicInputKinds, WrappedCircuit};
+    use crate::verify_committed;
+    use crate::test_util::{fr, product_witness, ProductCircuit};
+
+    #[test]
+    fn one_revealed_one_committed() {
+        // x * y = z with y and z public, and y committed.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let z = builder.wire();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+        let circuit: ProductCircuit = WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![y, z]);
+        let mut kinds = PublicInputKinds::new();
+        kinds.mark(y, PublicInputKind::Committed);
+        let (circuit, commitment) = circuit.with_committed_inputs(&kinds);
+        assert_eq!(commitment.committed, vec![y]);
+        assert_eq!(commitment.blinder, Wire { index: 4 });
+        assert_eq!(circuit.public_inputs(), &vec![z, commitment.commitment]);
+        assert_eq!(circuit.public_input_order(), vec![z, commitment.commitment]);
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        let blinder = fr(9);
+        let witness_map = circuit.with_witness_map(product_witness(2, 3, 6))
+            .with_commitment_witness(&commitment, blinder);
+        let proven = circuit.with_witness_map(witness_map.clone());
+        assert_eq!(proven.check_satisfied(), Ok(()));
+        let proof = prover.prove(witness_map, rng).unwrap();
+
+        // The verifier only sees z and the commitment.
+        let statement = proven.committed_statement(&commitment);
+        assert_eq!(statement.revealed, vec![fr(6)]);
+        assert_eq!(statement.commitment, ProductCircuit::commit_inputs(&[fr(3)], blinder));
+        let vk = &prover.parameters().vk;
+        assert!(verify_committed(vk, &proof, &statement).unwrap());
+
+        // The opening checks out, but not another value of y or another blinder.
+        assert_ne!(statement.commitment, ProductCircuit::commit_inputs(&[fr(4)], blinder));
+        assert_ne!(statement.commitment, ProductCircuit::commit_inputs(&[fr(3)], fr(10)));
+
+        // Nor does the proof verify against another revealed value or commitment.
+        let wrong_revealed = CommittedStatement { revealed: vec![fr(7)], ..statement.clone() };
+        assert!(!verify_committed(vk, &proof, &wrong_revealed).unwrap());
+        let wrong_commitment = CommittedStatement {
+            commitment: ProductCircuit::commit_inputs(&[fr(4)], blinder),
+            ..statement
+        };
+        assert!(!verify_committed(vk, &proof, &wrong_commitment).unwrap());
+
+        // A witness with another y, even one satisfying x * y = z, doesn't match the commitment.
+        let mut forged = proven.witness_map().clone();
+        forged.insert(x.index, fr(1));
+        forged.insert(y.index, fr(6));
+        assert!(circuit.with_witness_map(forged).check_satisfied().is_err());
+    }
+}
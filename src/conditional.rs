@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use bellman::{ConstraintSystem, LinearCombination, SynthesisError, Variable};
+use pairing::Engine;
+use pairing::ff::Field as _;
+use r1cs::{Constraint, Field, Wire};
+
+use crate::{FieldConverter, WrappedCircuit};
+
+/// A constraint `a * b = c` which is only enforced when `selector` is nonzero. It is synthesized
+/// as `a * b = t` and `selector * (t - c) = 0`, for a fresh auxiliary variable `t`, so when the
+/// selector is zero, `c` is left unconstrained.
+///
+/// Nothing constrains the selector itself; if it should be boolean, the gadget must assert that.
+#[derive(Clone, Debug)]
+pub struct ConditionalConstraint<F: Field> {
+    pub constraint: Constraint<F>,
+    pub selector: Wire,
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Adds a constraint which is only enforced when `selector` is nonzero. Conditional
+    /// constraints are synthesized after the gadget's constraints and any raw constraints, and
+    /// each allocates one auxiliary variable after all of the wires.
+    pub fn add_conditional_constraint(&mut self, constraint: Constraint<F>, selector: Wire) {
+        self.conditional_constraints.push(ConditionalConstraint { constraint, selector });
+    }
+
+    pub fn with_conditional_constraint(mut self, constraint: Constraint<F>, selector: Wire) -> Self {
+        self.add_conditional_constraint(constraint, selector);
+        self
+    }
+
+    pub fn conditional_constraints(&self) -> &[ConditionalConstraint<F>] {
+        &self.conditional_constraints
+    }
+
+    /// Returns whether a conditional constraint holds under the witness map: either its selector
+    /// is zero, or `a * b = c`.
+    pub(crate) fn conditional_satisfied(&self, conditional: &ConditionalConstraint<F>) -> bool {
+        let Constraint { a, b, c } = &conditional.constraint;
+        let mut product = self.evaluate(a);
+        product.mul_assign(&self.evaluate(b));
+        self.wire_value(conditional.selector).is_zero() || product == self.evaluate(c)
+    }
+
+    /// Returns the witness of each conditional constraint's auxiliary variable, `a * b`.
+    pub(crate) fn conditional_products(&self) -> Vec<E::Fr> {
+        self.conditional_constraints.iter()
+            .map(|conditional| {
+                let mut product = self.evaluate(&conditional.constraint.a);
+                product.mul_assign(&self.evaluate(&conditional.constraint.b));
+                product
+            })
+            .collect()
+    }
+
+    /// Enforces the conditional constraints. Every wire they reference must be in `variable_map`.
+    pub(crate) fn _synthesize_conditional<CS: ConstraintSystem<E>>(
+        cs: &mut CS,
+        conditionals: &[ConditionalConstraint<F>],
+        products: &[E::Fr],
+        variable_map: &BTreeMap<Wire, Variable>
+    ) -> Result<(), SynthesisError> {
+        // As in `_build_lc`, zero terms are left out.
+        let lc = |exp: &r1cs::Expression<F>| -> LinearCombination<E> {
+            let mut sum = LinearCombination::zero();
+            for (wire, coeff) in exp.coefficients() {
+                let fr = C::convert_field(coeff);
+                if fr.is_zero() {
+                    continue;
+                }
+                let var = if *wire == Wire::ONE { CS::one() } else { variable_map[wire] };
+                sum = sum + (fr, var);
+            }
+            sum
+        };
+        for (j, (conditional, product)) in conditionals.iter().zip(products).enumerate() {
+            let Constraint { a, b, c } = &conditional.constraint;
+            let t = cs.alloc(|| format!("conditional product {}", j), || Ok(*product))?;
+            cs.enforce(
+                || format!("conditional constraint {} product", j),
+                |_| lc(a),
+                |_| lc(b),
+                |lc| lc + t,
+            );
+            let selector = if conditional.selector == Wire::ONE {
+                CS::one()
+            } else {
+                variable_map[&conditional.selector]
+            };
+            let c = lc(c);
+            cs.enforce(
+                || format!("conditional constraint {}", j),
+                |lc| lc + selector,
+                |lc| lc + t - &c,
+                |lc| lc,
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use bellman::Circuit;
+    use pairing::compact_bn256::Bn256;
+    use r1cs::{Constraint, Element, Expression, GadgetBuilder, Wire, Bn128 as Bn128F};
+    use rand::thread_rng;
+
+    use crate::{CollectingConstraintSystem, Groth16Prover, WrappedCircuit};
+    use crate::test_util::{conditional_circuit, conditional_witness, fr, ProductCircuit};
+
+    #[test]
+    fn selector_enables_and_disables_constraint() {
+        // s ? x * y = z, with s and z public.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let z = builder.wire();
+        let s = builder.wire();
+        builder.assert_boolean(&Expression::from(&s));
+        let constraint = Constraint {
+            a: Expression::from(&x),
+            b: Expression::from(&y),
+            c: Expression::from(&z),
+        };
+        let circuit: ProductCircuit = WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![s, z])
+            .with_conditional_constraint(constraint, s);
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        for &(selector, product, satisfied) in &[(1u8, 6u8, true), (0, 7, true), (1, 7, false)] {
+            let mut witness_map = BTreeMap::new();
+            witness_map.insert(x.index, fr(2));
+            witness_map.insert(y.index, fr(3));
+            witness_map.insert(z.index, fr(product));
+            witness_map.insert(s.index, fr(selector));
+            let circuit = circuit.with_witness_map(witness_map);
+            assert_eq!(circuit.check_satisfied().is_ok(), satisfied);
+
+            let mut cs = CollectingConstraintSystem::<Bn256>::new();
+            circuit.with_witness_map(circuit.witness_map().clone()).synthesize(&mut cs).unwrap();
+            assert_eq!(cs.matrices(), circuit.to_matrices());
+
+            let proof = prover.prove(circuit.witness_map().clone(), rng).unwrap();
+            assert_eq!(prover.verify(&proof, &circuit.public_input_values()).unwrap(), satisfied);
+        }
+    }
+
+    #[test]
+    fn zero_terms_are_left_out() {
+        // s ? (x + 0 y) * x = w, alongside x * y = z.
+        let mut circuit = conditional_circuit(conditional_witness(2, 3, 6, 4, 1));
+        let mut constraint = circuit.conditional_constraints()[0].constraint.clone();
+        let mut a = BTreeMap::new();
+        a.insert(Wire { index: 1 }, Element::one());
+        a.insert(Wire { index: 2 }, Element::zero());
+        constraint.a = Expression::new(a);
+        circuit.conditional_constraints[0].constraint = constraint;
+
+        let mut cs = CollectingConstraintSystem::<Bn256>::new();
+        (&circuit).synthesize(&mut cs).unwrap();
+        let (a, _, _) = &cs.constraints[1];
+        assert_eq!(a.as_ref().len(), 1);
+        assert_eq!(cs.matrices(), circuit.to_matrices());
+    }
+}
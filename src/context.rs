@@ -0,0 +1,69 @@
+use num::BigUint;
+use pairing::Engine;
+use pairing::ff::PrimeField;
+use r1cs::{Constraint, Expression, Field, Wire};
+use sha2::{Digest, Sha256};
+
+use crate::{biguint_from_repr, fr_from_biguint, wire_for_key, FieldConverter, WrappedCircuit};
+
+/// Hashes a context string, such as an application name or a nonce, to a field element: its
+/// SHA-256 digest, reduced modulo the field's modulus.
+pub fn context_element<Fr: PrimeField>(context: &[u8]) -> Fr {
+    let digest = BigUint::from_bytes_le(&Sha256::digest(context));
+    fr_from_biguint(&(digest % biguint_from_repr(&Fr::char()))).expect("reduced below the modulus")
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Adds a public input for binding proofs to a context, so that a proof made in one context
+    /// can't be replayed in another. Returns the new wire, which is above every wire the circuit
+    /// uses, and whose witness should be `context_element` of the context. A constraint
+    /// `context * 1 = context` is added so that the wire is allocated; as with every public input,
+    /// the verification equation then depends on its value, so a proof only verifies when the
+    /// verifier supplies the same context.
+    ///
+    /// With any of this crate's wire orderings, the context is the last public input.
+    pub fn with_context_input(mut self) -> (Self, Wire) {
+        let max = self.referenced_wires().into_iter()
+            .chain(self.public_inputs().iter().cloned())
+            .chain(self.witness_map().keys().map(|key| wire_for_key(*key)))
+            .max()
+            .unwrap_or(Wire::ONE);
+        let wire = wire_for_key(max.index + 1);
+        let context = Expression::from(&wire);
+        self.preconverted = None;
+        self.gadget.constraints.push(Constraint {
+            a: context.clone(),
+            b: Expression::from(&Wire::ONE),
+            c: context,
+        });
+        self.public_inputs.push(wire);
+        (self, wire)
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use pairing::compact_bn256::Fr;
+    use rand::thread_rng;
+
+    use crate::{context_element, Groth16Prover};
+    use crate::test_util::{fr, product_circuit, product_witness};
+
+    #[test]
+    fn proof_is_bound_to_its_context() {
+        let (circuit, context) = product_circuit(BTreeMap::new()).with_context_input();
+        assert_eq!(context.index, 4);
+        assert_eq!(circuit.public_input_order().last(), Some(&context));
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        let mut witness_map = product_witness(2, 3, 6);
+        witness_map.insert(context.index, context_element::<Fr>(b"A"));
+        let proof = prover.prove(witness_map, rng).unwrap();
+
+        assert!(prover.verify(&proof, &[fr(6), context_element(b"A")]).unwrap());
+        assert!(!prover.verify(&proof, &[fr(6), context_element(b"B")]).unwrap());
+        assert_ne!(context_element::<Fr>(b"A"), context_element::<Fr>(b"B"));
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::{BTreeMap, HashSet};
+use std::iter;
+use bellman::{ConstraintSystem, LinearCombination, Variable};
+use pairing::Engine;
+use pairing::ff::Field as _;
+use r1cs::{Constraint, Expression, Field, Wire};
+
+use crate::{FieldConverter, WrappedCircuit};
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Sets whether `synthesize` allocates a variable for every wire index up to the largest one
+    /// the constraints reference, in one pass before enforcing any constraints, so that converting
+    /// a wire is a plain index into a vector rather than a lazy map lookup. This suits gadgets
+    /// which use nearly every index.
+    ///
+    /// Referenced wires are allocated in the usual order, followed by any unreferenced indices in
+    /// ascending order, as auxiliary variables. Where every index is referenced, the constraint
+    /// system is identical to the lazy one; otherwise the extra variables change it, and the
+    /// fingerprint, so parameters must be generated in the same mode.
+    pub fn with_preallocate_all(mut self, preallocate_all: bool) -> Self {
+        self.preallocate_all = preallocate_all;
+        self
+    }
+
+    pub fn preallocates_all(&self) -> bool {
+        self.preallocate_all
+    }
+
+    /// Returns the unreferenced wire indices `synthesize` allocates after the referenced wires, in
+    /// ascending order, given the circuit's `allocation_order`. This is empty unless the circuit
+    /// preallocates every index.
+    pub(crate) fn dense_padding(&self, allocation_order: &[Wire]) -> Vec<Wire> {
+        if !self.preallocate_all {
+            return Vec::new();
+        }
+        let referenced: HashSet<Wire> = allocation_order.iter().cloned().collect();
+        let max_index = referenced.iter().map(|wire| wire.index).max().unwrap_or(0);
+        (1..=max_index)
+            .map(|index| Wire { index })
+            .filter(|wire| !referenced.contains(wire))
+            .collect()
+    }
+
+    /// Allocates every wire index and enforces the gadget's constraints, or their preconverted
+    /// forms, returning the variables of the wires the conditional constraints reference.
+    pub(crate) fn _synthesize_dense<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        allocation_order: &[Wire]
+    ) -> BTreeMap<Wire, Variable> {
+        let public_inputs: HashSet<Wire> = self.public_inputs().iter().cloned().collect();
+        let max_index = allocation_order.iter().map(|wire| wire.index).max().unwrap_or(0);
+        let mut variables: Vec<Option<Variable>> = vec![None; max_index as usize + 1];
+        variables[Wire::ONE.index as usize] = Some(CS::one());
+        for wire in allocation_order {
+            variables[wire.index as usize] =
+                Some(Self::_generate_variable(cs, *wire, self.witness_map(), &public_inputs));
+        }
+        for wire in self.dense_padding(allocation_order) {
+            variables[wire.index as usize] =
+                Some(Self::_generate_variable(cs, wire, self.witness_map(), &HashSet::new()));
+        }
+        let variables: Vec<Variable> = variables.into_iter().map(Option::unwrap).collect();
+
+        match &self.preconverted {
+            Some(constraints) => {
+                let lc = |terms: &[(Wire, E::Fr)]| dense_lc::<E>(terms.iter().cloned(), &variables);
+                for (i, constraint) in constraints.iter().enumerate() {
+                    enforce(cs, i, lc(&constraint.a), lc(&constraint.b), lc(&constraint.c));
+                }
+            }
+            None => {
+                let lc = |exp: &Expression<F>| dense_lc::<E>(
+                    exp.coefficients().iter().map(|(wire, coeff)| (*wire, C::convert_field(coeff))),
+                    &variables);
+                for (i, constraint) in self.gadget().constraints.iter().enumerate() {
+                    enforce(cs, i, lc(&constraint.a), lc(&constraint.b), lc(&constraint.c));
+                }
+            }
+        }
+
+        let mut variable_map = BTreeMap::new();
+        for conditional in self.conditional_constraints() {
+            let Constraint { a, b, c } = &conditional.constraint;
+            let wires = a.coefficients().keys()
+                .chain(b.coefficients().keys())
+                .chain(c.coefficients().keys())
+                .chain(iter::once(&conditional.selector));
+            for wire in wires {
+                variable_map.insert(*wire, variables[wire.index as usize]);
+            }
+        }
+        variable_map
+    }
+}
+
+fn enforce<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    i: usize,
+    a_lc: LinearCombination<E>,
+    b_lc: LinearCombination<E>,
+    c_lc: LinearCombination<E>
+) {
+    cs.enforce(
+        || format!("generated by r1cs-bellman at {}", i),
+        |_| a_lc,
+        |_| b_lc,
+        |_| c_lc,
+    );
+}
+
+/// Builds a linear combination from converted terms, looking each wire's variable up by index.
+/// As in `_build_lc`, zero terms are left out.
+fn dense_lc<E: Engine>(
+    terms: impl IntoIterator<Item = (Wire, E::Fr)>,
+    variables: &[Variable]
+) -> LinearCombination<E> {
+    let mut sum = LinearCombination::zero();
+    for (wire, fr) in terms {
+        if !fr.is_zero() {
+            sum = sum + (fr, variables[wire.index as usize]);
+        }
+    }
+    sum
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use bellman::{Circuit, Index, Variable};
+    use pairing::compact_bn256::{Bn256, Fr};
+    use r1cs::{Constraint, Expression, GadgetBuilder, Wire, Bn128 as Bn128F};
+    use rand::thread_rng;
+
+    use crate::{CollectingConstraintSystem, Groth16Prover, R1csMatrices, WrappedCircuit};
+    use crate::test_util::{fr, ProductCircuit};
+
+    fn matrices(circuit: &ProductCircuit) -> R1csMatrices<Fr> {
+        let mut cs = CollectingConstraintSystem::<Bn256>::new();
+        circuit.with_witness_map(circuit.witness_map().clone()).synthesize(&mut cs).unwrap();
+        cs.matrices()
+    }
+
+    #[test]
+    fn preallocated_matches_lazy() {
+        // w_{i+1} = w_i * w_i for every index, so that every wire is referenced.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let w = builder.wires(10);
+        let mut witness_map = BTreeMap::new();
+        for pair in w.windows(2) {
+            let exp = Expression::from(&pair[0]);
+            builder.assert_product(&exp, &exp, &Expression::from(&pair[1]));
+        }
+        for wire in &w {
+            witness_map.insert(wire.index, fr(1));
+        }
+        let lazy: ProductCircuit = WrappedCircuit::new(builder.build(), witness_map, vec![w[9]]);
+        let dense = lazy.with_witness_map(lazy.witness_map().clone()).with_preallocate_all(true);
+        assert!(dense.preallocates_all());
+        assert_eq!(matrices(&dense), matrices(&lazy));
+        assert_eq!(dense.fingerprint(), lazy.fingerprint());
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&dense, rng).unwrap();
+        let proof = prover.prove(dense.witness_map().clone(), rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(1)]).unwrap());
+        assert!(!prover.verify(&proof, &[fr(2)]).unwrap());
+    }
+
+    #[test]
+    fn unreferenced_indices_are_auxiliary() {
+        // x * y = z, with an unused wire allocated before z.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        builder.wire();
+        let z = builder.wire();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+        let mut witness_map = BTreeMap::new();
+        for (wire, n) in &[(x, 2u8), (y, 3), (z, 6)] {
+            witness_map.insert(wire.index, fr(*n));
+        }
+        let circuit: ProductCircuit =
+            WrappedCircuit::new(builder.build(), witness_map, vec![z]).with_preallocate_all(true);
+
+        let mut cs = CollectingConstraintSystem::<Bn256>::new();
+        (&circuit).synthesize(&mut cs).unwrap();
+        assert_eq!((cs.inputs.len(), cs.aux.len()), (2, 3));
+        assert_eq!(cs.matrices(), circuit.to_matrices());
+        assert_eq!(circuit.variable_of(Wire { index: 3 }), Some(Variable::new_unchecked(Index::Aux(2))));
+        let lazy = circuit.with_witness_map(BTreeMap::new()).with_preallocate_all(false);
+        assert_ne!(circuit.fingerprint(), lazy.fingerprint());
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        let proof = prover.prove(circuit.witness_map().clone(), rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(6)]).unwrap());
+    }
+
+    #[test]
+    fn unreferenced_indices_precede_conditional_products() {
+        // x * y = z, and x * x = w when s is nonzero, with an unused wire allocated before z.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        builder.wire();
+        let z = builder.wire();
+        let w = builder.wire();
+        let s = builder.wire();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+        let square = Constraint {
+            a: Expression::from(&x),
+            b: Expression::from(&x),
+            c: Expression::from(&w),
+        };
+        let mut witness_map = BTreeMap::new();
+        for (wire, n) in &[(x, 2u8), (y, 3), (z, 6), (w, 4), (s, 1)] {
+            witness_map.insert(wire.index, fr(*n));
+        }
+        let circuit: ProductCircuit = WrappedCircuit::new(builder.build(), witness_map, vec![z])
+            .with_conditional_constraint(square, s)
+            .with_preallocate_all(true);
+
+        let mut cs = CollectingConstraintSystem::<Bn256>::new();
+        (&circuit).synthesize(&mut cs).unwrap();
+        // x, y, w and s, the unused wire, then the conditional product.
+        assert_eq!(cs.aux.len(), 6);
+        assert_eq!(cs.matrices(), circuit.to_matrices());
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        let proof = prover.prove(circuit.witness_map().clone(), rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(6)]).unwrap());
+    }
+}
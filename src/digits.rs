@@ -0,0 +1,195 @@
+//! Reading a `BigUint`'s digits in place.
+//!
+//! num-bigint 0.2, whose `BigUint` r1cs's elements are built on, can only hand out its digits by
+//! copying them into a `Vec`. Its serde form, though, is the sequence of its base-2^32 digits,
+//! least significant first, a format num-bigint has committed to keeping. Serializing into a sink
+//! which only accepts a sequence of `u32`s therefore visits the digits without allocating.
+
+use std::fmt;
+use num::BigUint;
+use serde::ser::{Impossible, Serialize, SerializeSeq, Serializer};
+
+/// Calls `f` with the index and value of each of `n`'s base-2^32 digits, least significant first,
+/// while it returns true. Returns whether every digit was visited.
+pub(crate) fn for_each_digit<F: FnMut(usize, u32) -> bool>(n: &BigUint, f: F) -> bool {
+    n.serialize(&mut DigitSink { f, index: 0 }).is_ok()
+}
+
+struct DigitSink<F> {
+    f: F,
+    index: usize,
+}
+
+/// Raised when the callback stops the visit, or if the serialized form isn't a sequence of `u32`s.
+#[derive(Debug)]
+struct Stop;
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "stopped visiting digits")
+    }
+}
+
+impl std::error::Error for Stop {}
+
+impl serde::ser::Error for Stop {
+    fn custom<T: fmt::Display>(_msg: T) -> Self {
+        Stop
+    }
+}
+
+/// Implements `Serializer` methods which reject the value, since a `BigUint` never produces it.
+macro_rules! reject {
+    ($($method:ident($($arg:ty),*)),* $(,)?) => {
+        $(fn $method(self, $(_: $arg),*) -> Result<(), Stop> {
+            Err(Stop)
+        })*
+    };
+}
+
+impl<'a, F: FnMut(usize, u32) -> bool> Serializer for &'a mut DigitSink<F> {
+    type Ok = ();
+    type Error = Stop;
+    type SerializeSeq = Self;
+    type SerializeTuple = Impossible<(), Stop>;
+    type SerializeTupleStruct = Impossible<(), Stop>;
+    type SerializeTupleVariant = Impossible<(), Stop>;
+    type SerializeMap = Impossible<(), Stop>;
+    type SerializeStruct = Impossible<(), Stop>;
+    type SerializeStructVariant = Impossible<(), Stop>;
+
+    fn serialize_u32(self, digit: u32) -> Result<(), Stop> {
+        let index = self.index;
+        self.index += 1;
+        if (self.f)(index, digit) {
+            Ok(())
+        } else {
+            Err(Stop)
+        }
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, Stop> {
+        Ok(self)
+    }
+
+    reject! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(&'static str),
+        serialize_unit_variant(&'static str, u32, &'static str),
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<(), Stop> {
+        Err(Stop)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _value: &T
+    ) -> Result<(), Stop> {
+        Err(Stop)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T
+    ) -> Result<(), Stop> {
+        Err(Stop)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Stop> {
+        Err(Stop)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize
+    ) -> Result<Self::SerializeTupleStruct, Stop> {
+        Err(Stop)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize
+    ) -> Result<Self::SerializeTupleVariant, Stop> {
+        Err(Stop)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Stop> {
+        Err(Stop)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, Stop> {
+        Err(Stop)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize
+    ) -> Result<Self::SerializeStructVariant, Stop> {
+        Err(Stop)
+    }
+}
+
+impl<'a, F: FnMut(usize, u32) -> bool> SerializeSeq for &'a mut DigitSink<F> {
+    type Ok = ();
+    type Error = Stop;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Stop> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Stop> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::{BigUint, One};
+
+    use super::for_each_digit;
+
+    #[test]
+    fn digits_least_significant_first() {
+        let n = (BigUint::one() << 96) + (BigUint::from(3u8) << 32) + BigUint::from(7u8);
+        let mut digits = Vec::new();
+        assert!(for_each_digit(&n, |i, digit| {
+            digits.push((i, digit));
+            true
+        }));
+        assert_eq!(digits, vec![(0, 7), (1, 3), (2, 0), (3, 1)]);
+
+        let mut visited = 0;
+        assert!(!for_each_digit(&n, |i, _| {
+            visited += 1;
+            i < 1
+        }));
+        assert_eq!(visited, 2);
+        assert!(for_each_digit(&BigUint::from(0u8), |_, _| false));
+    }
+}
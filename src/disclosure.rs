@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+use pairing::Engine;
+use r1cs::{Field, Gadget, Wire};
+
+use crate::{witness_key, FieldConverter, WrappedCircuit};
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Returns the sub-circuit of the constraints which don't reference any of `hidden`, for
+    /// proving a statement about part of a larger witness. The hidden wires are dropped from the
+    /// witness and the public inputs, so neither the proof nor the sub-circuit's structure reveals
+    /// anything about them.
+    ///
+    /// A proof for the sub-circuit only attests to the constraints it keeps. Anything the dropped
+    /// constraints said, including how the hidden wires relate to the others, is not proven, and
+    /// a wire the dropped constraints determined may be unconstrained in the sub-circuit. The
+    /// sub-circuit needs its own setup, and conditional and raw constraints are not carried over.
+    pub fn sub_circuit(&self, hidden: &[Wire]) -> Self {
+        let hidden: HashSet<Wire> = hidden.iter().cloned().collect();
+        let hidden_keys: HashSet<u32> = hidden.iter().map(|wire| witness_key(*wire)).collect();
+        let constraints = self.gadget().constraints.iter()
+            .filter(|constraint| [&constraint.a, &constraint.b, &constraint.c].iter()
+                .all(|exp| exp.coefficients().keys().all(|wire| !hidden.contains(wire))))
+            .cloned()
+            .collect();
+        let witness_map = self.witness_map().iter()
+            .filter(|(index, _)| !hidden_keys.contains(*index))
+            .map(|(index, value)| (*index, *value))
+            .collect();
+        let public_inputs = self.public_inputs().iter().cloned()
+            .filter(|wire| !hidden.contains(wire))
+            .collect();
+        let gadget = Gadget { constraints, witness_generators: Vec::new() };
+        let mut circuit = Self::new(gadget, witness_map, public_inputs);
+        circuit.wire_ordering = self.wire_ordering.clone();
+        circuit.metadata = self.metadata().clone();
+        circuit
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use r1cs::{Expression, GadgetBuilder, Bn128 as Bn128F};
+    use rand::thread_rng;
+
+    use crate::{Groth16Prover, WrappedCircuit};
+    use crate::test_util::{fr, ProductCircuit};
+
+    #[test]
+    fn prove_sub_statement() {
+        // x * y = t, t * s = u, with t and u public. Only the first statement is disclosed.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let t = builder.wire();
+        let s = builder.wire();
+        let u = builder.wire();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&t));
+        builder.assert_product(&Expression::from(&t), &Expression::from(&s), &Expression::from(&u));
+        let witness_map = vec![(x, 2u8), (y, 3), (t, 6), (s, 5), (u, 30)].into_iter()
+            .map(|(wire, n)| (wire.index, fr(n)))
+            .collect();
+        let circuit: ProductCircuit = WrappedCircuit::new(builder.build(), witness_map, vec![t, u]);
+        assert_eq!(circuit.check_satisfied(), Ok(()));
+
+        let sub = circuit.sub_circuit(&[s, u]);
+        assert_eq!(sub.gadget().constraints.len(), 1);
+        assert_eq!(sub.public_inputs(), &vec![t]);
+        assert_eq!(sub.witness_map().len(), 3);
+        assert!(!sub.witness_map().contains_key(&s.index));
+        assert_eq!(sub.check_satisfied(), Ok(()));
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&sub, rng).unwrap();
+        let proof = prover.prove(sub.witness_map().clone(), rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(6)]).unwrap());
+    }
+}
@@ -0,0 +1,305 @@
+//! Proving with an engine chosen at runtime, such as from a `--curve` command line flag.
+
+#[cfg(feature = "prover")]
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+use bellman::groth16::{Proof, VerifyingKey};
+#[cfg(feature = "prover")]
+use num::BigUint;
+use pairing::Engine;
+use pairing::bls12_381::Bls12;
+use pairing::compact_bn256::Bn256;
+#[cfg(feature = "prover")]
+use r1cs::Field;
+#[cfg(feature = "prover")]
+use rand::Rng;
+
+use crate::{CachedVerifier, Error, VerificationBundle};
+#[cfg(feature = "prover")]
+use crate::{engine_modulus, fr_from_biguint, Groth16Prover, SameFieldConverter, WrappedCircuit};
+
+/// The engines `prove_dynamic` supports, each paired with the r1cs field of the same modulus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    Bn256,
+    Bls12_381,
+}
+
+impl FromStr for Curve {
+    type Err = Error;
+
+    /// Parses a curve name, ignoring case: `bn256` (or `bn254`, or `bn128`), or `bls12-381`.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s.to_lowercase().as_str() {
+            "bn256" | "bn254" | "bn128" => Ok(Curve::Bn256),
+            "bls12-381" | "bls12_381" => Ok(Curve::Bls12_381),
+            _ => Err(Error::InvalidFormat(format!("unknown curve {}", s))),
+        }
+    }
+}
+
+impl fmt::Display for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Curve::Bn256 => write!(f, "bn256"),
+            Curve::Bls12_381 => write!(f, "bls12-381"),
+        }
+    }
+}
+
+impl Curve {
+    fn tag(self) -> u8 {
+        match self {
+            Curve::Bn256 => 0,
+            Curve::Bls12_381 => 1,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(Curve::Bn256),
+            1 => Some(Curve::Bls12_381),
+            _ => None,
+        }
+    }
+}
+
+/// An engine with a `Curve` tag.
+pub trait TaggedEngine: Engine {
+    const CURVE: Curve;
+}
+
+impl TaggedEngine for Bn256 {
+    const CURVE: Curve = Curve::Bn256;
+}
+
+impl TaggedEngine for Bls12 {
+    const CURVE: Curve = Curve::Bls12_381;
+}
+
+/// A serialized proof tagged with the curve it was made on, so that a proof for one engine is
+/// rejected clearly when checked against another engine's verifying key, rather than failing to
+/// deserialize or verify. Its layout is a one byte tag, followed by the proof as written by
+/// `Proof::write`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofEnvelope {
+    curve: Curve,
+    proof: Vec<u8>,
+}
+
+impl ProofEnvelope {
+    pub fn new<E: TaggedEngine>(proof: &Proof<E>) -> Self {
+        let mut bytes = Vec::new();
+        proof.write(&mut bytes).expect("writing to a Vec can't fail");
+        Self { curve: E::CURVE, proof: bytes }
+    }
+
+    pub fn curve(&self) -> Curve {
+        self.curve
+    }
+
+    /// Returns the enclosed proof, if it was made on `E`.
+    pub fn open<E: TaggedEngine>(&self) -> Result<Proof<E>, Error> {
+        if self.curve != E::CURVE {
+            return Err(Error::EngineMismatch { expected: E::CURVE, actual: self.curve });
+        }
+        Ok(Proof::read(&self.proof[..])?)
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&[self.curve.tag()])?;
+        writer.write_all(&self.proof)
+    }
+
+    /// Reads an envelope, checking its tag but not its proof, which is only decoded by `open`.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut tag = [0u8; 1];
+        reader.read_exact(&mut tag)?;
+        let curve = Curve::from_tag(tag[0])
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unknown curve tag"))?;
+        let mut proof = Vec::new();
+        reader.read_to_end(&mut proof)?;
+        Ok(Self { curve, proof })
+    }
+}
+
+impl<E: TaggedEngine> CachedVerifier<E> {
+    /// Verifies an enveloped proof, failing with `EngineMismatch` before any decoding or pairing
+    /// if it was made on a different engine.
+    pub fn verify_envelope(
+        &self,
+        envelope: &ProofEnvelope,
+        public_inputs: &[E::Fr]
+    ) -> Result<bool, Error> {
+        let proof = envelope.open::<E>()?;
+        Ok(self.verify(&proof, public_inputs)?)
+    }
+}
+
+/// A proof tagged with the curve it was made on, bundled with its public inputs. As with any
+/// `VerificationBundle`, its verifying key must be checked against a trusted one.
+pub enum DynProof {
+    Bn256(VerificationBundle<Bn256>),
+    Bls12_381(VerificationBundle<Bls12>),
+}
+
+/// A verifying key for an engine chosen at runtime.
+pub enum DynVerifyingKey {
+    Bn256(VerifyingKey<Bn256>),
+    Bls12_381(VerifyingKey<Bls12>),
+}
+
+impl DynVerifyingKey {
+    pub fn curve(&self) -> Curve {
+        match self {
+            DynVerifyingKey::Bn256(_) => Curve::Bn256,
+            DynVerifyingKey::Bls12_381(_) => Curve::Bls12_381,
+        }
+    }
+}
+
+impl DynProof {
+    pub fn curve(&self) -> Curve {
+        match self {
+            DynProof::Bn256(_) => Curve::Bn256,
+            DynProof::Bls12_381(_) => Curve::Bls12_381,
+        }
+    }
+
+    /// Verifies the proof with a trusted verifying key, as `VerificationBundle::verify` does,
+    /// failing with `EngineMismatch` if the key is for a different curve.
+    pub fn verify(&self, trusted_vk: &DynVerifyingKey) -> Result<bool, Error> {
+        match (self, trusted_vk) {
+            (DynProof::Bn256(bundle), DynVerifyingKey::Bn256(vk)) => bundle.verify(vk),
+            (DynProof::Bls12_381(bundle), DynVerifyingKey::Bls12_381(vk)) => bundle.verify(vk),
+            _ => Err(Error::EngineMismatch { expected: trusted_vk.curve(), actual: self.curve() }),
+        }
+    }
+}
+
+/// Proves a circuit exported by `to_json` on the given curve. The circuit's coefficients are
+/// interpreted in the r1cs field matching the curve. Its exported witness, if any, is replaced by
+/// `witness`, whose values are decimal strings.
+///
+/// This runs a fresh setup for each proof, with random parameters, so it suits tools and tests
+/// rather than deployments; the returned proof carries its own verifying key.
+#[cfg(feature = "prover")]
+pub fn prove_dynamic<R: Rng>(
+    curve: Curve,
+    circuit_json: &str,
+    witness: &BTreeMap<u32, String>,
+    rng: &mut R
+) -> Result<DynProof, Error> {
+    match curve {
+        Curve::Bn256 =>
+            prove_on::<r1cs::Bn128, Bn256, R>(circuit_json, witness, rng).map(DynProof::Bn256),
+        Curve::Bls12_381 =>
+            prove_on::<r1cs::Bls12_381, Bls12, R>(circuit_json, witness, rng).map(DynProof::Bls12_381),
+    }
+}
+
+#[cfg(feature = "prover")]
+fn prove_on<F: Field, E: Engine, R: Rng>(
+    circuit_json: &str,
+    witness: &BTreeMap<u32, String>,
+    rng: &mut R
+) -> Result<VerificationBundle<E>, Error> {
+    let circuit = WrappedCircuit::<F, E, SameFieldConverter>::from_json(circuit_json)?;
+    let modulus = engine_modulus::<E>();
+    let mut witness_map = BTreeMap::new();
+    for (index, value) in witness {
+        let value = value.parse::<BigUint>()
+            .map_err(|_| Error::InvalidFormat(format!("witness {} isn't a decimal integer", index)))?;
+        let fr = fr_from_biguint(&value)
+            .ok_or_else(|| Error::ElementOutOfRange { value: value.clone(), modulus: modulus.clone() })?;
+        witness_map.insert(*index, fr);
+    }
+    let prover = Groth16Prover::setup(&circuit, rng)?;
+    prover.prove_bundle(witness_map, rng)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use pairing::bls12_381::Bls12;
+    use r1cs::{Expression, GadgetBuilder, Bls12_381};
+    use rand::thread_rng;
+
+    use crate::{prove_dynamic, CachedVerifier, Curve, DynProof, DynVerifyingKey, Error, Groth16Prover};
+    use crate::ProofEnvelope;
+    use crate::{SameFieldConverter, WrappedCircuit};
+    use crate::test_util::{fr, product_circuit, product_witness};
+
+    #[test]
+    fn prove_on_curve_chosen_at_runtime() {
+        let json = bls_product_json();
+
+        let curve: Curve = "BLS12-381".parse().unwrap();
+        assert_eq!(curve, Curve::Bls12_381);
+        assert!("secp256k1".parse::<Curve>().is_err());
+
+        let witness: BTreeMap<u32, String> =
+            vec![(1, "2"), (2, "3"), (3, "6")].into_iter().map(|(i, v)| (i, v.to_string())).collect();
+        let rng = &mut thread_rng();
+        let proof = prove_dynamic(curve, &json, &witness, rng).unwrap();
+        assert_eq!(proof.curve(), Curve::Bls12_381);
+        // Each call runs its own setup, so the key a tool would publish comes with the proof.
+        let published_key = |proof: &DynProof| match proof {
+            DynProof::Bls12_381(bundle) => DynVerifyingKey::Bls12_381(bundle.vk.clone()),
+            DynProof::Bn256(bundle) => DynVerifyingKey::Bn256(bundle.vk.clone()),
+        };
+        let trusted_vk = published_key(&proof);
+        assert!(proof.verify(&trusted_vk).unwrap());
+
+        let mut bad_witness = witness;
+        bad_witness.insert(3, "7".to_string());
+        let bad_proof = prove_dynamic(curve, &json, &bad_witness, rng).unwrap();
+        assert!(!bad_proof.verify(&published_key(&bad_proof)).unwrap());
+        // A proof under a key of its maker's choosing isn't accepted under the trusted key.
+        assert!(matches!(bad_proof.verify(&trusted_vk), Err(Error::VerifyingKeyMismatch)));
+    }
+
+    #[test]
+    fn envelope_rejects_other_engine() {
+        let rng = &mut thread_rng();
+        let bn_prover = Groth16Prover::setup(&product_circuit(BTreeMap::new()), rng).unwrap();
+        let bn_verifier = CachedVerifier::new(&bn_prover.parameters().vk);
+
+        let bn_proof = bn_prover.prove(product_witness(2, 3, 6), rng).unwrap();
+        let bn_envelope = ProofEnvelope::new(&bn_proof);
+        let mut bytes = vec![];
+        bn_envelope.write(&mut bytes).unwrap();
+        let bn_envelope = ProofEnvelope::read(&bytes[..]).unwrap();
+        assert_eq!(bn_envelope.curve(), Curve::Bn256);
+        assert!(bn_verifier.verify_envelope(&bn_envelope, &[fr(6)]).unwrap());
+
+        let witness: BTreeMap<u32, String> =
+            vec![(1, "2"), (2, "3"), (3, "6")].into_iter().map(|(i, v)| (i, v.to_string())).collect();
+        let bls_bundle = prove_dynamic(Curve::Bls12_381, &bls_product_json(), &witness, rng).unwrap();
+        let bls_proof = match bls_bundle {
+            DynProof::Bls12_381(bundle) => bundle.proof,
+            DynProof::Bn256(_) => unreachable!(),
+        };
+        let bls_envelope = ProofEnvelope::new::<Bls12>(&bls_proof);
+        match bn_verifier.verify_envelope(&bls_envelope, &[fr(6)]) {
+            Err(Error::EngineMismatch { expected, actual }) => {
+                assert_eq!(expected, Curve::Bn256);
+                assert_eq!(actual, Curve::Bls12_381);
+            }
+            _ => panic!("expected an engine mismatch"),
+        }
+    }
+
+    fn bls_product_json() -> String {
+        let mut builder = GadgetBuilder::<Bls12_381>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let z = builder.wire();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+        let circuit: WrappedCircuit<Bls12_381, Bls12, SameFieldConverter> =
+            WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![z]);
+        circuit.to_json()
+    }
+}
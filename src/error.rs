@@ -0,0 +1,111 @@
+use std::error;
+use std::fmt;
+use std::io;
+use bellman::SynthesisError;
+use num::BigUint;
+use r1cs::Wire;
+
+use crate::{Curve, Fingerprint};
+
+#[derive(Debug)]
+pub enum Error {
+    /// An error returned by bellman.
+    Synthesis(SynthesisError),
+    /// The circuit being proven is not structurally the one the parameters were generated for.
+    FingerprintMismatch { expected: Fingerprint, actual: Fingerprint },
+    /// An element was too large to fit in the engine's scalar field.
+    ElementOutOfRange { value: BigUint, modulus: BigUint },
+    /// Proving was refused because these wires, which the constraints reference, have no witness.
+    MissingWitness(Vec<Wire>),
+    /// A freshly created proof failed to verify against its own public inputs.
+    SelfVerificationFailed,
+    /// An exported circuit or other serialized data could not be parsed.
+    InvalidFormat(String),
+    /// A public input order wasn't a permutation of the circuit's declared public inputs.
+    InvalidPublicInputOrder,
+    /// Reading a file failed.
+    Io(io::Error),
+    /// A converted element didn't convert back to the integer it was converted from. The location
+    /// is the index of the constraint and the wire of the coefficient, if the element was one.
+    ConversionCorruption { location: Option<(usize, Wire)>, value: BigUint, converted: BigUint },
+    /// A proof was made on a different engine from the one it was checked against.
+    EngineMismatch { expected: Curve, actual: Curve },
+    /// A proof was verified with a different number of public inputs than its circuit exposes.
+    PublicInputCountMismatch { expected: usize, actual: usize },
+    /// A circuit didn't match its manifest. These are the manifest fields which differed.
+    ManifestMismatch(Vec<String>),
+    /// Externally supplied parameters don't have the shape of the circuit they were given for.
+    ParametersMismatch,
+    /// A bundled verifying key isn't the trusted key it was verified with.
+    VerifyingKeyMismatch,
+}
+
+impl From<SynthesisError> for Error {
+    fn from(e: SynthesisError) -> Self {
+        Error::Synthesis(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Synthesis(e) => write!(f, "synthesis error: {}", e),
+            Error::FingerprintMismatch { .. } =>
+                write!(f, "the circuit's structure differs from the one used for setup"),
+            Error::ElementOutOfRange { value, modulus } =>
+                write!(f, "element {} does not fit in a field of modulus {}", value, modulus),
+            Error::MissingWitness(wires) =>
+                write!(f, "{} referenced wires have no witness", wires.len()),
+            Error::SelfVerificationFailed =>
+                write!(f, "the proof failed to verify against its own public inputs"),
+            Error::InvalidFormat(reason) => write!(f, "invalid format: {}", reason),
+            Error::InvalidPublicInputOrder =>
+                write!(f, "the order isn't a permutation of the declared public inputs"),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::ConversionCorruption { location: Some((i, wire)), value, converted } =>
+                write!(f, "the coefficient of wire {} in constraint {} converted from {} to {}",
+                    wire.index, i, value, converted),
+            Error::ConversionCorruption { location: None, value, converted } =>
+                write!(f, "{} converted to {}", value, converted),
+            Error::EngineMismatch { expected, actual } =>
+                write!(f, "expected a proof on {}, but it was made on {}", expected, actual),
+            Error::PublicInputCountMismatch { expected, actual } =>
+                write!(f, "expected {} public inputs, but got {}", expected, actual),
+            Error::ParametersMismatch =>
+                write!(f, "the parameters weren't generated for a circuit of this shape"),
+            Error::ManifestMismatch(fields) =>
+                write!(f, "the circuit doesn't match its manifest in: {}", fields.join(", ")),
+            Error::VerifyingKeyMismatch =>
+                write!(f, "the bundled verifying key isn't the trusted one"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// Why a serialized proof was rejected by `validate_proof_format`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    /// The proof is not the length of a serialized proof for the engine.
+    WrongLength { expected: usize, actual: usize },
+    /// A group element isn't a valid, nonzero point in the right subgroup.
+    InvalidPoint,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FormatError::WrongLength { expected, actual } =>
+                write!(f, "expected a {} byte proof, but got {} bytes", expected, actual),
+            FormatError::InvalidPoint => write!(f, "the proof contains an invalid group element"),
+        }
+    }
+}
+
+impl error::Error for FormatError {}
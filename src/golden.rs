@@ -0,0 +1,192 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt;
+use pairing::Engine;
+use r1cs::{Field, Wire};
+
+use crate::{FieldConverter, WrappedCircuit};
+use crate::json::constraint_to_json;
+
+/// How a circuit differs from a golden reference. Constraints present in only one of the two are
+/// counted as changed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diff {
+    pub public_inputs_changed: bool,
+    pub changed_constraints: Vec<usize>,
+    lines: Vec<String>,
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.lines.join("\n"))
+    }
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Returns the circuit's golden form, for committing to a repository: its constraint stream,
+    /// as written by `write_constraint_stream`. Only its structure is included, not its witness,
+    /// metadata or wire ordering.
+    pub fn golden(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_constraint_stream(&mut bytes).expect("writing to a Vec can't fail");
+        bytes
+    }
+
+    /// Compares the circuit against a golden form from `golden`, returning a description of the
+    /// differences, if any.
+    pub fn assert_matches_golden(&self, golden_bytes: &[u8]) -> Result<(), Diff> {
+        let actual = self.golden();
+        let golden = String::from_utf8_lossy(golden_bytes);
+        let actual = String::from_utf8_lossy(&actual);
+        let mut golden = golden.lines();
+        let mut actual = actual.lines();
+
+        let mut diff = Diff {
+            public_inputs_changed: false,
+            changed_constraints: Vec::new(),
+            lines: Vec::new(),
+        };
+        let (golden_header, actual_header) = (golden.next(), actual.next());
+        if golden_header != actual_header {
+            diff.public_inputs_changed = true;
+            diff.lines.push(format!("header: golden {}, actual {}",
+                golden_header.unwrap_or("(none)"), actual_header.unwrap_or("(none)")));
+        }
+        let mut i = 0;
+        loop {
+            match (golden.next(), actual.next()) {
+                (None, None) => break,
+                (expected, found) if expected != found => {
+                    diff.changed_constraints.push(i);
+                    diff.lines.push(format!("constraint {}: golden {}, actual {}",
+                        i, expected.unwrap_or("(none)"), found.unwrap_or("(none)")));
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+
+        if diff.lines.is_empty() {
+            Ok(())
+        } else {
+            Err(diff)
+        }
+    }
+}
+
+/// The constraint level changes between two versions of a circuit, as found by `circuit_diff`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CircuitDiff {
+    /// The indices, in the new circuit, of constraints which the old one lacks.
+    pub added: Vec<usize>,
+    /// The indices, in the old circuit, of constraints which the new one lacks.
+    pub removed: Vec<usize>,
+    /// The indices of constraints which differ between the two at the same position.
+    pub modified: Vec<usize>,
+    pub public_inputs_added: Vec<Wire>,
+    pub public_inputs_removed: Vec<Wire>,
+}
+
+impl CircuitDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Compares the gadget constraints of two versions of a circuit. Constraints are matched by their
+/// canonical form, as in the JSON export, wherever they appear, so moving a constraint isn't a
+/// change. Of the unmatched ones, a pair at the same index counts as modified, and the rest as
+/// added or removed. Public inputs are compared as sets.
+pub fn circuit_diff<F, E, C>(old: &WrappedCircuit<F, E, C>, new: &WrappedCircuit<F, E, C>) -> CircuitDiff
+where
+    F: Field,
+    E: Engine,
+    C: FieldConverter<F, E>,
+{
+    let canonical = |circuit: &WrappedCircuit<F, E, C>| -> Vec<String> {
+        circuit.gadget().constraints.iter()
+            .map(|constraint| constraint_to_json(constraint).to_string())
+            .collect()
+    };
+    let (old_constraints, new_constraints) = (canonical(old), canonical(new));
+    let mut unmatched_old: HashMap<&str, VecDeque<usize>> = HashMap::new();
+    for (i, constraint) in old_constraints.iter().enumerate() {
+        unmatched_old.entry(constraint.as_str()).or_insert_with(VecDeque::new).push_back(i);
+    }
+    let unmatched_new: BTreeSet<usize> = new_constraints.iter().enumerate()
+        .filter(|(_, constraint)| {
+            unmatched_old.get_mut(constraint.as_str()).and_then(|indices| indices.pop_front()).is_none()
+        })
+        .map(|(i, _)| i)
+        .collect();
+    let unmatched_old: BTreeSet<usize> = unmatched_old.values().flatten().cloned().collect();
+
+    let modified: Vec<usize> = unmatched_old.intersection(&unmatched_new).cloned().collect();
+    let old_inputs: BTreeSet<Wire> = old.public_inputs().iter().cloned().collect();
+    let new_inputs: BTreeSet<Wire> = new.public_inputs().iter().cloned().collect();
+    CircuitDiff {
+        added: unmatched_new.iter().filter(|i| !modified.contains(i)).cloned().collect(),
+        removed: unmatched_old.iter().filter(|i| !modified.contains(i)).cloned().collect(),
+        modified,
+        public_inputs_added: new_inputs.difference(&old_inputs).cloned().collect(),
+        public_inputs_removed: old_inputs.difference(&new_inputs).cloned().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use r1cs::{Element, Expression, GadgetBuilder, Wire, Bn128 as Bn128F};
+
+    use crate::{circuit_diff, CircuitDiff, WrappedCircuit};
+    use crate::test_util::{product_circuit, product_witness, scaled, ProductCircuit};
+
+    #[test]
+    fn detects_changed_constraint() {
+        let golden = product_circuit(BTreeMap::new()).golden();
+        assert_eq!(product_circuit(product_witness(2, 3, 6)).assert_matches_golden(&golden), Ok(()));
+
+        // The same shape, but z is scaled by 2.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let z = builder.wire();
+        let two_z = scaled(z, Element::from(2u8));
+        builder.assert_product(&Expression::from(&x), &Expression::from(&y), &two_z);
+        let modified: ProductCircuit = WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![z]);
+        let diff = modified.assert_matches_golden(&golden).unwrap_err();
+        assert!(!diff.public_inputs_changed);
+        assert_eq!(diff.changed_constraints, vec![0]);
+        assert!(diff.to_string().starts_with("constraint 0: golden"));
+
+        let extended = ProductCircuit::new(
+            r1cs::Gadget {
+                constraints: vec![product_circuit(BTreeMap::new()).gadget().constraints[0].clone(); 2],
+                witness_generators: Vec::new(),
+            },
+            BTreeMap::new(),
+            vec![]);
+        let diff = extended.assert_matches_golden(&golden).unwrap_err();
+        assert!(diff.public_inputs_changed);
+        assert_eq!(diff.changed_constraints, vec![1]);
+    }
+
+    #[test]
+    fn diff_reports_added_constraint() {
+        let old = product_circuit(BTreeMap::new());
+        assert!(circuit_diff(&old, &old).is_empty());
+
+        // The same product, with an extra constraint z * 1 = z.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let z = builder.wire();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+        builder.assert_product(&Expression::from(&z), &Expression::from(&Wire::ONE), &Expression::from(&z));
+        let new: ProductCircuit = WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![z]);
+
+        let expected = CircuitDiff { added: vec![1], ..CircuitDiff::default() };
+        assert_eq!(circuit_diff(&old, &new), expected);
+        let expected = CircuitDiff { removed: vec![1], ..CircuitDiff::default() };
+        assert_eq!(circuit_diff(&new, &old), expected);
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::BTreeMap;
+use num::BigUint;
+use pairing::Engine;
+use r1cs::{Constraint, Element, Expression, Field, Gadget, Wire};
+use serde_json::{json, Map, Value};
+
+use crate::{biguint_from_fr, fr_from_biguint, Error, FieldConverter, WrappedCircuit};
+
+/// The version of the JSON export format.
+pub const JSON_FORMAT_VERSION: u64 = 1;
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Exports the circuit as JSON: its constraints, public inputs, witness and metadata. Field
+    /// elements are written as decimal strings, and each expression as a list of
+    /// `[wire index, coefficient]` terms. Object keys are sorted, so equal circuits always export
+    /// identically.
+    ///
+    /// Raw constraints, conditional constraints and the wire ordering strategy are not part of the
+    /// export.
+    pub fn to_json(&self) -> String {
+        let constraints: Vec<Value> = self.gadget().constraints.iter().map(constraint_to_json).collect();
+        let public_inputs: Vec<u32> = self.public_inputs().iter().map(|wire| wire.index).collect();
+        let witness: Map<String, Value> = self.witness_map().iter()
+            .map(|(index, value)| (index.to_string(), Value::String(biguint_from_fr(value).to_string())))
+            .collect();
+        json!({
+            "version": JSON_FORMAT_VERSION,
+            "metadata": self.metadata(),
+            "public_inputs": public_inputs,
+            "constraints": constraints,
+            "witness": witness,
+        }).to_string()
+    }
+
+    /// Imports a circuit exported by `to_json`.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let value: Value = serde_json::from_str(json).map_err(|e| invalid(&e.to_string()))?;
+        if value["version"].as_u64() != Some(JSON_FORMAT_VERSION) {
+            return Err(invalid("unsupported version"));
+        }
+
+        let constraints = value["constraints"].as_array().ok_or_else(|| invalid("missing constraints"))?
+            .iter()
+            .map(constraint_from_json)
+            .collect::<Result<Vec<_>, Error>>()?;
+        let public_inputs = value["public_inputs"].as_array().ok_or_else(|| invalid("missing public inputs"))?
+            .iter()
+            .map(|index| Ok(Wire { index: parse_index(index)? }))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let mut witness_map = BTreeMap::new();
+        for (index, value) in value["witness"].as_object().ok_or_else(|| invalid("missing witness"))? {
+            let index = index.parse::<u32>().map_err(|_| invalid("invalid witness index"))?;
+            let value = fr_from_biguint(&parse_biguint(value)?)
+                .ok_or_else(|| invalid("witness value out of range"))?;
+            witness_map.insert(index, value);
+        }
+
+        let gadget = Gadget { constraints, witness_generators: Vec::new() };
+        let mut circuit = Self::new(gadget, witness_map, public_inputs);
+        for (key, value) in value["metadata"].as_object().ok_or_else(|| invalid("missing metadata"))? {
+            let value = value.as_str().ok_or_else(|| invalid("metadata values must be strings"))?;
+            circuit.set_metadata(key.as_str(), value);
+        }
+        Ok(circuit)
+    }
+}
+
+pub(crate) fn constraint_to_json<F: Field>(constraint: &Constraint<F>) -> Value {
+    let expression = |exp: &Expression<F>| -> Value {
+        exp.coefficients().iter()
+            .map(|(wire, coeff)| json!([wire.index, coeff.to_biguint().to_string()]))
+            .collect()
+    };
+    json!({
+        "a": expression(&constraint.a),
+        "b": expression(&constraint.b),
+        "c": expression(&constraint.c),
+    })
+}
+
+pub(crate) fn constraint_from_json<F: Field>(value: &Value) -> Result<Constraint<F>, Error> {
+    Ok(Constraint {
+        a: parse_expression(&value["a"])?,
+        b: parse_expression(&value["b"])?,
+        c: parse_expression(&value["c"])?,
+    })
+}
+
+fn parse_expression<F: Field>(value: &Value) -> Result<Expression<F>, Error> {
+    let mut coefficients = BTreeMap::new();
+    for term in value.as_array().ok_or_else(|| invalid("expressions must be lists of terms"))? {
+        let wire = Wire { index: parse_index(&term[0])? };
+        let coeff = parse_biguint(&term[1])?;
+        if coeff >= F::order() {
+            return Err(invalid("coefficient out of range"));
+        }
+        coefficients.insert(wire, Element::from(coeff));
+    }
+    Ok(Expression::new(coefficients))
+}
+
+pub(crate) fn parse_index(value: &Value) -> Result<u32, Error> {
+    value.as_u64()
+        .filter(|index| *index <= u32::max_value() as u64)
+        .map(|index| index as u32)
+        .ok_or_else(|| invalid("invalid wire index"))
+}
+
+fn parse_biguint(value: &Value) -> Result<BigUint, Error> {
+    value.as_str()
+        .and_then(|s| s.parse::<BigUint>().ok())
+        .ok_or_else(|| invalid("field elements must be decimal strings"))
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidFormat(reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::test_util::{product_circuit, product_witness, ProductCircuit};
+
+    #[test]
+    fn metadata_round_trips_without_changing_fingerprint() {
+        let circuit = product_circuit(product_witness(2, 3, 6))
+            .with_metadata("name", "product")
+            .with_metadata("version", "1.2.0");
+        assert_eq!(circuit.fingerprint(), product_circuit(BTreeMap::new()).fingerprint());
+
+        let json = circuit.to_json();
+        let imported = ProductCircuit::from_json(&json).unwrap();
+        assert_eq!(imported.metadata(), circuit.metadata());
+        assert_eq!(imported.metadata()["name"], "product");
+        assert_eq!(imported.witness_map(), circuit.witness_map());
+        assert_eq!(imported.public_inputs(), circuit.public_inputs());
+        assert_eq!(imported.fingerprint(), circuit.fingerprint());
+        assert_eq!(imported.to_json(), json);
+    }
+
+    #[test]
+    fn rejects_invalid_json() {
+        assert!(ProductCircuit::from_json("{}").is_err());
+        assert!(ProductCircuit::from_json("not json").is_err());
+    }
+}
@@ -1,13 +1,16 @@
 use std::collections::{HashSet,BTreeMap};
 use std::iter::FromIterator;
-use bellman::{Circuit, ConstraintSystem, LinearCombination, SynthesisError, Variable};
+use sha2::{Digest, Sha256};
+use bellman::{Circuit, ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
 use bellman::Index::{Aux, Input};
 use bellman::SynthesisError::{AssignmentMissing};
 use pairing::{
     Engine,
     ff::{
+        Field as _,
         ScalarEngine,
-        PrimeField
+        PrimeField,
+        PrimeFieldRepr
     },
     compact_bn256::{
         Bn256,
@@ -15,47 +18,240 @@ use pairing::{
         Fr
     }
 };
-use r1cs::{Constraint, Element, Expression, Field, Gadget, Wire, Bn128 as Bn128F};
+use r1cs::{Constraint, Element, Expression, Field, Gadget, Wire, WireValues, Bn128 as Bn128F};
 use std::marker::PhantomData;
+use std::sync::Arc;
 use num::{BigUint, Integer, One, ToPrimitive};
 
+mod analysis;
+#[cfg(feature = "arkworks")]
+mod arkworks;
+mod audit;
+mod bundle;
+mod checked;
+mod collect;
+mod commitment;
+mod conditional;
+mod context;
+mod dense;
+mod digits;
+mod disclosure;
+mod dynamic;
+mod error;
+mod golden;
+mod json;
+mod manifest;
+mod matrices;
+mod memory;
+mod modulus;
+mod ordering;
+#[cfg(feature = "plonk")]
+mod plonk;
+mod preconverted;
+mod proof_stream;
+#[cfg(feature = "prover")]
+mod prover;
+mod raw;
+mod recording;
+mod reference;
+mod report;
+mod serialize;
+mod source;
+mod stream;
+mod verify;
+mod witness;
+mod witness_buffer;
+#[cfg(test)]
+mod test_util;
+
+pub use analysis::SharedProductRewrite;
+#[cfg(feature = "arkworks")]
+pub use arkworks::ArkCircuit;
+pub use audit::AuditRecord;
+pub use bundle::{VerificationBundle, VerifyingKeyBundle};
+pub use checked::CheckedConverter;
+pub use collect::CollectingConstraintSystem;
+pub use commitment::{verify_committed, CommittedStatement, InputCommitment, PublicInputKind, PublicInputKinds};
+pub use conditional::ConditionalConstraint;
+pub use context::context_element;
+#[cfg(feature = "prover")]
+pub use dynamic::prove_dynamic;
+pub use dynamic::{Curve, DynProof, DynVerifyingKey, ProofEnvelope, TaggedEngine};
+pub use error::{Error, FormatError};
+pub use golden::{circuit_diff, CircuitDiff, Diff};
+pub use json::JSON_FORMAT_VERSION;
+pub use manifest::{verify_manifest, write_manifest};
+pub use matrices::R1csMatrices;
+pub use memory::MemoryEstimate;
+pub use modulus::ModulusCheck;
+pub use ordering::{FirstUseOrder, PublicFirst, SortedByIndex, WireOrdering};
+#[cfg(feature = "plonk")]
+pub use plonk::{PlonkCircuit, PlonkGate};
+pub use preconverted::FrConstraint;
+pub use proof_stream::{write_proof_frame, ProofStreamVerifier};
+#[cfg(feature = "prover")]
+pub use prover::{generate_test_parameters, prove_and_verify, Groth16Prover};
+pub use raw::{one_variable, RawConstraint};
+pub use recording::{looks_negative, LintingConverter, RecordingConverter};
+pub use reference::WrappedCircuitRef;
+pub use report::REPORT_EDGE_CONSTRAINTS;
+pub use serialize::{deserialize_public_inputs, read_public_inputs, serialize_public_inputs, write_public_inputs};
+pub use source::WitnessSource;
+pub use stream::StreamedCircuit;
+pub use verify::{proof_length, public_input_commitment, validate_proof_format, verify_any, CachedVerifier};
+pub use witness::{public_inputs_for_verification, public_inputs_from_gadget, witness_from_signed_map};
+pub use witness_buffer::{witness_from_buffer, MappedWitness};
+
+/// A digest of a circuit's structure: its constraints and public inputs, but not its witness.
+pub type Fingerprint = [u8; 32];
+
 pub trait FieldConverter<F: Field, E: Engine> {
     fn convert_field(n: &Element<F>) -> E::Fr;
+
+    /// Returns whether every element of `F` can be converted by this converter. This is checked
+    /// when a `WrappedCircuit` is constructed.
+    fn is_compatible() -> bool {
+        true
+    }
+}
+
+/// Converts a `BigUint` into an element of `Fr`, or `None` if it is not less than `Fr`'s modulus.
+pub fn fr_from_biguint<Fr: PrimeField>(n: &BigUint) -> Option<Fr> {
+    let mut repr = Fr::Repr::default();
+    let bytes = n.to_bytes_le();
+    if bytes.len() > repr.as_ref().len() * 8 {
+        return None;
+    }
+    // The repr's limbs are little endian, as are the bytes within each limb.
+    for (i, byte) in bytes.iter().enumerate() {
+        repr.as_mut()[i / 8] |= (*byte as u64) << (8 * (i % 8));
+    }
+    Fr::from_repr(repr).ok()
+}
+
+/// The number of 64-bit limbs in a BN256 scalar field repr.
+pub const BN256_LIMBS: usize = 4;
+
+/// The number of 64-bit limbs in a BLS12-381 scalar field repr.
+pub const BLS12_381_LIMBS: usize = 4;
+
+/// Splits `n` into `LIMBS` little endian 64-bit limbs, or returns `None` if it doesn't fit. The
+/// limbs are assembled in a stack array straight from `n`'s digits, without allocating.
+pub fn limbs_from_biguint<const LIMBS: usize>(n: &BigUint) -> Option<[u64; LIMBS]> {
+    let mut limbs = [0u64; LIMBS];
+    let fits = digits::for_each_digit(n, |i, digit| match limbs.get_mut(i / 2) {
+        Some(limb) => {
+            *limb |= (digit as u64) << (32 * (i % 2));
+            true
+        }
+        None => digit == 0,
+    });
+    if fits {
+        Some(limbs)
+    } else {
+        None
+    }
+}
+
+/// Converts an element into `Fr` without allocating, or returns `None` if it is not less than
+/// `Fr`'s modulus, or if `LIMBS` isn't the number of limbs in `Fr`'s repr. This is monomorphized
+/// per limb count, and assembles the repr from a stack array.
+pub fn convert_element<F: Field, Fr: PrimeField, const LIMBS: usize>(n: &Element<F>) -> Option<Fr> {
+    let mut repr = Fr::Repr::default();
+    if repr.as_ref().len() != LIMBS {
+        return None;
+    }
+    let limbs = limbs_from_biguint::<LIMBS>(n.to_biguint())?;
+    repr.as_mut().copy_from_slice(&limbs);
+    Fr::from_repr(repr).ok()
+}
+
+/// Converts an element of `Fr` into its canonical integer representation.
+pub fn biguint_from_fr<Fr: PrimeField>(fr: &Fr) -> BigUint {
+    biguint_from_repr(&fr.into_repr())
+}
+
+/// Converts an element of `Fr` into an element of the r1cs field `F`, failing if it is too large.
+pub fn element_from_fr<F: Field, Fr: PrimeField>(fr: &Fr) -> Result<Element<F>, Error> {
+    let n = biguint_from_fr(fr);
+    if n >= F::order() {
+        return Err(Error::ElementOutOfRange { value: n, modulus: F::order() });
+    }
+    Ok(Element::from(n))
+}
+
+fn biguint_from_repr<R: PrimeFieldRepr>(repr: &R) -> BigUint {
+    let mut bytes = Vec::with_capacity(repr.as_ref().len() * 8);
+    for limb in repr.as_ref() {
+        bytes.extend_from_slice(&limb.to_le_bytes());
+    }
+    BigUint::from_bytes_le(&bytes)
+}
+
+/// Returns whether the r1cs field `F` has the same modulus as `E`'s scalar field.
+pub fn moduli_match<F: Field, E: Engine>() -> bool {
+    F::order() == engine_modulus::<E>()
+}
+
+/// Returns the modulus of `E`'s scalar field.
+pub fn engine_modulus<E: Engine>() -> BigUint {
+    biguint_from_repr(&<E::Fr as PrimeField>::char())
 }
 
 pub struct WrappedCircuit<F: Field, E: Engine, C:FieldConverter<F,E>> {
     gadget: Gadget<F>,
     witness_map: BTreeMap<u32,E::Fr>,
     public_inputs: Vec<Wire>,
+    raw_constraints: Vec<RawConstraint<E>>,
+    conditional_constraints: Vec<ConditionalConstraint<F>>,
+    /// The gadget's constraints with their coefficients already in `E::Fr`, if known.
+    preconverted: Option<Arc<Vec<FrConstraint<E>>>>,
+    wire_ordering: Arc<dyn WireOrdering + Send + Sync>,
+    /// Whether `synthesize` allocates every wire index up front. See `with_preallocate_all`.
+    preallocate_all: bool,
+    metadata: BTreeMap<String, String>,
+    warnings: Vec<Warning>,
     _c: PhantomData<C>
 }
 
+/// A likely mistake noticed while constructing a circuit, which was worked around.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Warning {
+    /// The constant wire was listed as a public input. It is always bound to bellman's constant
+    /// one, which verifiers supply implicitly, so it was dropped from the public inputs.
+    ConstantWireInPublicInputs,
+}
+
 impl<F: Field, E: Engine, C:FieldConverter<F,E>> Circuit<E> for WrappedCircuit<F, E, C> {
     fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
-        let WrappedCircuit { gadget, witness_map, public_inputs, _c} = self;
-        let public_inputs = HashSet::from_iter(public_inputs);
-        let mut variable_map: BTreeMap<Wire,Variable> = BTreeMap::<Wire,Variable>::new();
-        /*for i in 0..n_wire {
-            let wire_index = (i+1) as u32;
-            let wire = Wire{index:wire_index};
-            let variable = Self::_generate_variable(cs, wire, &witness_map, &public_inputs);
-            variable_map.insert(wire, variable);
-        }*/
-        let mut i=0;
-        for constraint in gadget.constraints {
-            let Constraint { a, b, c } = constraint;
-            let a_lc = Self::_convert_lc::<CS>(cs, a, &mut variable_map, &witness_map, &public_inputs);
-            let b_lc = Self::_convert_lc::<CS>(cs, b, &mut variable_map, &witness_map, &public_inputs);
-            let c_lc = Self::_convert_lc::<CS>(cs, c, &mut variable_map, &witness_map, &public_inputs);
+        (&self).synthesize(cs)
+    }
+}
+
+/// Synthesizing a borrowed circuit lets it be synthesized repeatedly, such as for a setup and then
+/// to check its shape, without copying it for each synthesis.
+impl<'a, F: Field, E: Engine, C:FieldConverter<F,E>> Circuit<E> for &'a WrappedCircuit<F, E, C> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let allocation_order = self.allocation_order();
+        let conditional_products = self.conditional_products();
+        let public_inputs = HashSet::from_iter(self.public_inputs.iter().cloned());
+        let variable_map = match (self.preallocate_all, &self.preconverted) {
+            (true, _) => self._synthesize_dense(cs, &allocation_order),
+            (false, Some(preconverted)) => WrappedCircuit::<F, E, C>::_synthesize_preconverted(
+                cs, preconverted, &self.witness_map, &public_inputs, &allocation_order),
+            (false, None) => WrappedCircuit::<F, E, C>::_synthesize_constraints(
+                cs, &self.gadget.constraints, &self.witness_map, &public_inputs, &allocation_order),
+        };
+        for (j, raw) in self.raw_constraints.iter().enumerate() {
             cs.enforce(
-                || format!("generated by r1cs-bellman at {}", i),
-                |_| a_lc,
-                |_| b_lc,
-                |_| c_lc,
+                || format!("raw constraint {}", j),
+                |_| raw.a.clone(),
+                |_| raw.b.clone(),
+                |_| raw.c.clone(),
             );
-            i += 1;
         }
-        Ok(())
+        WrappedCircuit::<F, E, C>::_synthesize_conditional(
+            cs, &self.conditional_constraints, &conditional_products, &variable_map)
     }
 }
 
@@ -65,14 +261,54 @@ impl<F: Field, E: Engine, C:FieldConverter<F,E>> WrappedCircuit<F, E, C> {
         witness_map: BTreeMap<u32,E::Fr>,
         public_inputs: Vec<Wire>
     ) -> Self {
+        assert!(C::is_compatible(), "the field converter doesn't support this field and engine");
+        let mut warnings = Vec::new();
+        if public_inputs.contains(&Wire::ONE) {
+            warnings.push(Warning::ConstantWireInPublicInputs);
+        }
+        let public_inputs = public_inputs.into_iter().filter(|wire| *wire != Wire::ONE).collect();
         Self {
             gadget,
             witness_map,
             public_inputs,
+            raw_constraints: Vec::new(),
+            conditional_constraints: Vec::new(),
+            preconverted: None,
+            wire_ordering: Arc::new(FirstUseOrder),
+            preallocate_all: false,
+            metadata: BTreeMap::new(),
+            warnings,
             _c: PhantomData
         }
     }
 
+    /// Returns any likely mistakes noticed while constructing the circuit.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Attaches a metadata entry, such as a circuit name or version, for downstream tooling.
+    /// Metadata is carried into exports and bundles, but never affects the constraint system or
+    /// the fingerprint.
+    pub fn with_metadata<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.set_metadata(key, value);
+        self
+    }
+
+    pub fn set_metadata<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) {
+        self.metadata.insert(key.into(), value.into());
+    }
+
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// Sets the order in which wires are allocated as bellman variables. See `WireOrdering`.
+    pub fn with_wire_ordering<O: WireOrdering + Send + Sync + 'static>(mut self, ordering: O) -> Self {
+        self.wire_ordering = Arc::new(ordering);
+        self
+    }
+
     pub fn gadget(&self) -> &Gadget<F> {
         &self.gadget
     }
@@ -85,47 +321,330 @@ impl<F: Field, E: Engine, C:FieldConverter<F,E>> WrappedCircuit<F, E, C> {
         &self.public_inputs
     }
 
-    fn _convert_lc<CS: ConstraintSystem<E>>(
+    /// Returns a circuit with the same constraints and public inputs, but a different witness.
+    pub fn with_witness_map(&self, witness_map: BTreeMap<u32,E::Fr>) -> Self {
+        let gadget = Gadget {
+            constraints: self.gadget.constraints.clone(),
+            witness_generators: Vec::new(),
+        };
+        let mut circuit = Self::new(gadget, witness_map, self.public_inputs.clone());
+        circuit.raw_constraints = self.raw_constraints.clone();
+        circuit.conditional_constraints = self.conditional_constraints.clone();
+        circuit.preconverted = self.preconverted.clone();
+        circuit.wire_ordering = self.wire_ordering.clone();
+        circuit.preallocate_all = self.preallocate_all;
+        circuit.metadata = self.metadata.clone();
+        circuit.warnings = self.warnings.clone();
+        circuit
+    }
+
+    /// Returns every wire the constraints reference, in the order `synthesize` allocates them, as
+    /// determined by the circuit's `WireOrdering`.
+    pub fn allocation_order(&self) -> Vec<Wire> {
+        self.wire_ordering.order(&self.first_use_order(), &self.public_inputs)
+    }
+
+    /// Returns every wire the constraints reference, in the order they are first referenced. The
+    /// constant wire is excluded, since it maps to bellman's constant one rather than being
+    /// allocated.
+    fn first_use_order(&self) -> Vec<Wire> {
+        let mut seen = HashSet::new();
+        seen.insert(Wire::ONE);
+        let mut order = Vec::new();
+        let conditionals = self.conditional_constraints.iter().map(|conditional| &conditional.constraint);
+        for constraint in self.gadget.constraints.iter().chain(conditionals) {
+            for exp in &[&constraint.a, &constraint.b, &constraint.c] {
+                for wire in exp.coefficients().keys() {
+                    if seen.insert(*wire) {
+                        order.push(*wire);
+                    }
+                }
+            }
+        }
+        for conditional in &self.conditional_constraints {
+            if seen.insert(conditional.selector) {
+                order.push(conditional.selector);
+            }
+        }
+        order
+    }
+
+    /// Returns the bellman index `synthesize` allocates for each wire, in allocation order, followed
+    /// by any unreferenced indices a circuit preallocating every index allocates. Input 0 is
+    /// bellman's constant one, to which the constant wire maps, so public inputs are numbered from
+    /// 1, and private wires from 0.
+    pub fn variable_indices(&self) -> Vec<(Wire, Index)> {
+        let public_inputs: HashSet<&Wire> = self.public_inputs.iter().collect();
+        let allocation_order = self.allocation_order();
+        let padding: Vec<(Wire, bool)> = self.dense_padding(&allocation_order).into_iter()
+            .map(|wire| (wire, false))
+            .collect();
+        let mut num_inputs = 1;
+        let mut num_aux = 0;
+        allocation_order.into_iter()
+            .map(|wire| (wire, public_inputs.contains(&wire)))
+            .chain(padding)
+            .map(|(wire, public)| {
+                let index = if public {
+                    num_inputs += 1;
+                    Input(num_inputs - 1)
+                } else {
+                    num_aux += 1;
+                    Aux(num_aux - 1)
+                };
+                (wire, index)
+            })
+            .collect()
+    }
+
+    /// Returns the public input wires in the order `synthesize` allocates them, which is the order
+    /// bellman expects their values in when verifying. Public inputs which no constraint
+    /// references are never allocated, so they are omitted.
+    pub fn public_input_order(&self) -> Vec<Wire> {
+        self.allocation_order().into_iter()
+            .filter(|wire| self.public_inputs.contains(wire))
+            .collect()
+    }
+
+    /// Returns the witness values of the public inputs, in the order a verifier must supply them.
+    pub fn public_input_values(&self) -> Vec<E::Fr> {
+        self.public_input_order().iter()
+            .map(|wire| self.wire_value(*wire))
+            .collect()
+    }
+
+    /// Checks that the witness agrees with public input values supplied by the verifier, in the
+    /// order of `public_input_order`, returning the index of the first which differs. If the
+    /// verifier supplied too few or too many values, the first index past the shorter list is
+    /// returned.
+    pub fn assert_public_inputs_match(&self, verifier_inputs: &[E::Fr]) -> Result<(), usize> {
+        let values = self.public_input_values();
+        let mismatch = values.iter().zip(verifier_inputs)
+            .position(|(value, input)| value != input);
+        match mismatch {
+            Some(i) => Err(i),
+            None if values.len() != verifier_inputs.len() => Err(values.len().min(verifier_inputs.len())),
+            None => Ok(()),
+        }
+    }
+
+    /// Computes a digest of the circuit's constraints and public inputs. Two circuits with the same
+    /// fingerprint synthesize to the same constraint system, so they can share parameters.
+    pub fn fingerprint(&self) -> Fingerprint {
+        let mut hasher = Sha256::new();
+        hasher.update(&(self.public_inputs.len() as u32).to_le_bytes());
+        for wire in &self.public_inputs {
+            hasher.update(&wire.index.to_le_bytes());
+        }
+        hasher.update(&(self.gadget.constraints.len() as u32).to_le_bytes());
+        for constraint in &self.gadget.constraints {
+            hash_constraint(&mut hasher, constraint);
+        }
+        // The allocation order determines each wire's column, so it affects the parameters too.
+        let allocation_order = self.allocation_order();
+        for wire in &allocation_order {
+            hasher.update(&wire.index.to_le_bytes());
+        }
+        let padding = self.dense_padding(&allocation_order);
+        hasher.update(&(padding.len() as u32).to_le_bytes());
+        for wire in padding {
+            hasher.update(&wire.index.to_le_bytes());
+        }
+        hasher.update(&(self.raw_constraints.len() as u32).to_le_bytes());
+        for raw in &self.raw_constraints {
+            raw.hash_into(&mut hasher);
+        }
+        hasher.update(&(self.conditional_constraints.len() as u32).to_le_bytes());
+        for conditional in &self.conditional_constraints {
+            hasher.update(&conditional.selector.index.to_le_bytes());
+            hash_constraint(&mut hasher, &conditional.constraint);
+        }
+        let mut fingerprint = [0u8; 32];
+        fingerprint.copy_from_slice(&hasher.finalize());
+        fingerprint
+    }
+
+    /// Checks every constraint against the witness map, evaluating in `E::Fr` after conversion.
+    /// Returns the index of the first unsatisfied constraint, if any, with conditional constraints
+    /// numbered after the gadget's constraints. As in `synthesize`, wires without a witness are
+    /// treated as zero.
+    pub fn check_satisfied(&self) -> Result<(), usize> {
+        if let Some(i) = self.gadget.constraints.iter().position(|constraint| !self.satisfied(constraint)) {
+            return Err(i);
+        }
+        let unsatisfied = self.conditional_constraints.iter()
+            .position(|conditional| !self.conditional_satisfied(conditional));
+        match unsatisfied {
+            Some(j) => Err(self.gadget.constraints.len() + j),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns whether each constraint is satisfied by the witness map, evaluating every one rather
+    /// than stopping at the first failure, numbered as in `check_satisfied`.
+    pub fn satisfaction_map(&self) -> Vec<bool> {
+        let conditionals = self.conditional_constraints.iter()
+            .map(|conditional| self.conditional_satisfied(conditional));
+        self.gadget.constraints.iter()
+            .map(|constraint| self.satisfied(constraint))
+            .chain(conditionals)
+            .collect()
+    }
+
+    fn satisfied(&self, constraint: &Constraint<F>) -> bool {
+        let mut product = self.evaluate(&constraint.a);
+        product.mul_assign(&self.evaluate(&constraint.b));
+        product == self.evaluate(&constraint.c)
+    }
+
+    /// Checks every constraint against `values`, evaluating in the r1cs field `F` with r1cs's own
+    /// arithmetic, so that no conversion is involved. Returns the index of the first unsatisfied
+    /// constraint, if any, numbered as in `check_satisfied`. `values` must assign every wire the
+    /// constraints reference.
+    pub fn check_satisfied_in_field(&self, values: &WireValues<F>) -> Result<(), usize> {
+        if let Some(i) = self.gadget.constraints.iter().position(|constraint| !constraint.evaluate(values)) {
+            return Err(i);
+        }
+        let unsatisfied = self.conditional_constraints.iter().position(|conditional| {
+            *values.get(conditional.selector) != Element::zero() && !conditional.constraint.evaluate(values)
+        });
+        match unsatisfied {
+            Some(j) => Err(self.gadget.constraints.len() + j),
+            None => Ok(()),
+        }
+    }
+
+    fn evaluate(&self, exp: &Expression<F>) -> E::Fr {
+        let mut sum = E::Fr::zero();
+        for (wire, coeff) in exp.coefficients() {
+            let mut term = C::convert_field(coeff);
+            term.mul_assign(&self.wire_value(*wire));
+            sum.add_assign(&term);
+        }
+        sum
+    }
+
+    fn wire_value(&self, wire: Wire) -> E::Fr {
+        if wire == Wire::ONE {
+            return E::Fr::one();
+        }
+        witness_of(&self.witness_map, wire).cloned().unwrap_or_else(E::Fr::zero)
+    }
+
+    pub(crate) fn _synthesize_constraints<CS: ConstraintSystem<E>, W: WitnessSource<E::Fr> + ?Sized>(
+        cs: &mut CS,
+        constraints: &[Constraint<F>],
+        witness: &W,
+        public_inputs: &HashSet<Wire>,
+        preallocated: &[Wire]
+    ) -> BTreeMap<Wire,Variable> {
+        let mut variable_map = Self::_preallocate(cs, witness, public_inputs, preallocated);
+        for (i, constraint) in constraints.iter().enumerate() {
+            Self::_enforce_constraint(cs, i, constraint, &mut variable_map, witness, public_inputs);
+        }
+        variable_map
+    }
+
+    /// Allocates the wires in `preallocated` up front, in that order; any others are allocated
+    /// lazily, as they are first referenced.
+    fn _preallocate<CS: ConstraintSystem<E>, W: WitnessSource<E::Fr> + ?Sized>(
+        cs: &mut CS,
+        witness: &W,
+        public_inputs: &HashSet<Wire>,
+        preallocated: &[Wire]
+    ) -> BTreeMap<Wire,Variable> {
+        let mut variable_map: BTreeMap<Wire,Variable> = BTreeMap::<Wire,Variable>::new();
+        for wire in preallocated {
+            let variable = Self::_generate_variable(cs, *wire, witness, public_inputs);
+            variable_map.insert(*wire, variable);
+        }
+        variable_map
+    }
+
+    /// Enforces the `i`th constraint, allocating any wires it references which aren't yet in
+    /// `variable_map`.
+    pub(crate) fn _enforce_constraint<CS: ConstraintSystem<E>, W: WitnessSource<E::Fr> + ?Sized>(
         cs: &mut CS,
-        exp: Expression<F>,
+        i: usize,
+        constraint: &Constraint<F>,
         variable_map: &mut BTreeMap<Wire,Variable>,
-        witness_map: &BTreeMap<u32,E::Fr>,
+        witness: &W,
+        public_inputs: &HashSet<Wire>
+    ) {
+        let Constraint { a, b, c } = constraint;
+        let a_lc = Self::_convert_lc::<CS, W>(cs, a, variable_map, witness, public_inputs);
+        let b_lc = Self::_convert_lc::<CS, W>(cs, b, variable_map, witness, public_inputs);
+        let c_lc = Self::_convert_lc::<CS, W>(cs, c, variable_map, witness, public_inputs);
+        cs.enforce(
+            || format!("generated by r1cs-bellman at {}", i),
+            |_| a_lc,
+            |_| b_lc,
+            |_| c_lc,
+        );
+    }
+
+    fn _convert_lc<CS: ConstraintSystem<E>, W: WitnessSource<E::Fr> + ?Sized>(
+        cs: &mut CS,
+        exp: &Expression<F>,
+        variable_map: &mut BTreeMap<Wire,Variable>,
+        witness: &W,
+        public_inputs: &HashSet<Wire>
+    ) -> LinearCombination<E> {
+        let terms = exp.coefficients().iter().map(|(wire, coeff)| (*wire, C::convert_field(coeff)));
+        Self::_build_lc(cs, terms, variable_map, witness, public_inputs)
+    }
+
+    /// Builds a linear combination from terms whose coefficients are already converted.
+    fn _build_lc<CS: ConstraintSystem<E>, W: WitnessSource<E::Fr> + ?Sized>(
+        cs: &mut CS,
+        terms: impl IntoIterator<Item = (Wire, E::Fr)>,
+        variable_map: &mut BTreeMap<Wire,Variable>,
+        witness: &W,
         public_inputs: &HashSet<Wire>
     ) -> LinearCombination<E> {
         // This is inefficient, but bellman doesn't expose a LinearCombination constructor taking an
         // entire variable/coefficient map, so we have to build one up with repeated addition.
         let mut sum = LinearCombination::zero();
-        for (wire, coeff) in exp.coefficients() {
-            let fr = C::convert_field(coeff);
-            let var = match variable_map.get(wire) {
+        for (wire, fr) in terms {
+            if wire == Wire::ONE {
+                if !fr.is_zero() {
+                    sum = sum + (fr, CS::one());
+                }
+                continue;
+            }
+            let var = match variable_map.get(&wire) {
                 Some(variable) => *variable,
                 None => {
-                    let new_val = Self::_generate_variable(cs,*wire,witness_map,public_inputs);
-                    variable_map.insert(*wire,new_val);
+                    let new_val = Self::_generate_variable(cs,wire,witness,public_inputs);
+                    variable_map.insert(wire,new_val);
                     new_val
                 }
             };
-            sum = sum + (fr, var);
+            // Zero terms, such as the unselected entries of a one-hot lookup, are left out of the
+            // linear combination, though their wires are still allocated, so that allocation
+            // doesn't depend on coefficient values.
+            if !fr.is_zero() {
+                sum = sum + (fr, var);
+            }
         }
         sum
     }
     
-    fn _generate_variable<CS: ConstraintSystem<E>>(
+    fn _generate_variable<CS: ConstraintSystem<E>, W: WitnessSource<E::Fr> + ?Sized>(
         cs: &mut CS,
         wire: Wire,
-        witness_map: &BTreeMap<u32,E::Fr>,
+        witness: &W,
         public_inputs: &HashSet<Wire>
     ) -> Variable {
-        let wire_index = wire.index;
-        let witness = witness_map.get(&wire_index);
+        let witness = witness.witness(wire);
         let is_public = public_inputs.contains(&wire);
         
         match witness {
             Some(wtns) => {
                 if is_public {
-                    cs.alloc_input(|| "public input", || Ok(*wtns)).unwrap()
+                    cs.alloc_input(|| "public input", || Ok(wtns)).unwrap()
                 } else {
-                    cs.alloc(|| "private input", || Ok(*wtns)).unwrap()
+                    cs.alloc(|| "private input", || Ok(wtns)).unwrap()
                 }
             }
             None => {
@@ -139,6 +658,33 @@ impl<F: Field, E: Engine, C:FieldConverter<F,E>> WrappedCircuit<F, E, C> {
     }
 }
 
+/// Returns the key of `wire` in a witness map. Witness maps are keyed by index for callers'
+/// convenience, but everything else handles wires through r1cs's `Wire`, so this and
+/// `wire_for_key` are the only places which depend on how wires are addressed.
+pub(crate) fn witness_key(wire: Wire) -> u32 {
+    wire.index
+}
+
+pub(crate) fn wire_for_key(key: u32) -> Wire {
+    Wire { index: key }
+}
+
+pub(crate) fn witness_of<Fr>(witness_map: &BTreeMap<u32, Fr>, wire: Wire) -> Option<&Fr> {
+    witness_map.get(&witness_key(wire))
+}
+
+fn hash_constraint<F: Field>(hasher: &mut Sha256, constraint: &Constraint<F>) {
+    for exp in &[&constraint.a, &constraint.b, &constraint.c] {
+        hasher.update(&(exp.coefficients().len() as u32).to_le_bytes());
+        for (wire, coeff) in exp.coefficients() {
+            let coeff_bytes = coeff.to_biguint().to_bytes_le();
+            hasher.update(&wire.index.to_le_bytes());
+            hasher.update(&(coeff_bytes.len() as u32).to_le_bytes());
+            hasher.update(&coeff_bytes);
+        }
+    }
+}
+
 pub struct Bn256Converter;
 
 impl FieldConverter<Bn128F, Bn256> for Bn256Converter {
@@ -152,15 +698,27 @@ impl FieldConverter<Bn128F, Bn256> for Bn256Converter {
             (n >> 64 * 2).mod_floor(&u64_size).to_u64().unwrap(),
             (n >> 64 * 3).mod_floor(&u64_size).to_u64().unwrap(),
         ];
-        println!("chunks:{:?}",chunks);
         <<Bn256 as ScalarEngine>::Fr as PrimeField>::from_repr(FrRepr(chunks)).unwrap()
     }
 }
 
+/// A converter for the case where `F` and `E::Fr` are the same field. Since every element of `F`
+/// is then already a canonical element of `E::Fr`, its integer is copied straight into a repr,
+/// without any reduction.
+pub struct SameFieldConverter;
 
+impl<F: Field, E: Engine> FieldConverter<F, E> for SameFieldConverter {
+    fn convert_field(n: &Element<F>) -> E::Fr {
+        fr_from_biguint(n.to_biguint())
+            .expect("SameFieldConverter used with a field larger than the engine's scalar field")
+    }
 
+    fn is_compatible() -> bool {
+        moduli_match::<F, E>()
+    }
+}
 
-#[cfg(test)]
+#[cfg(all(test, feature = "prover"))]
 mod tests {
     use bellman::groth16::{create_random_proof, generate_random_parameters, prepare_verifying_key, Proof, verify_proof};
     use num::{BigUint, Integer, One, ToPrimitive};
@@ -176,12 +734,18 @@ mod tests {
             Fr
         }
     };
-    use r1cs::{Element, Expression, Field, Gadget, GadgetBuilder,  Wire, Bn128 as Bn128F};
+    use r1cs::{Element, Expression, Field, Gadget, GadgetBuilder,  Wire, WireValues, Bn128 as Bn128F};
     use rand::thread_rng;
+    use pairing::ff::Field as _;
     use std::collections::{BTreeMap};
     use std::marker::PhantomData;
 
-    use crate::{WrappedCircuit, Bn256Converter, FieldConverter};
+    use crate::{WrappedCircuit, Bn256Converter, FieldConverter, SameFieldConverter, moduli_match};
+    use crate::{convert_element, fr_from_biguint, limbs_from_biguint, BLS12_381_LIMBS, BN256_LIMBS};
+    use crate::{biguint_from_fr, element_from_fr, CollectingConstraintSystem, Groth16Prover, Warning};
+    use bellman::Circuit;
+    use pairing::ff::SqrtField;
+    use crate::test_util::{conditional_circuit, conditional_witness, fr, product_circuit, product_witness, scaled};
 
     #[test]
     fn valid_proof() {
@@ -213,6 +777,369 @@ mod tests {
         assert!(verify_proof(&pvk, &proof, public_inputs).is_ok());
     }
 
+    #[test]
+    fn same_field_conversion_matches_general_path() {
+        assert!(moduli_match::<Bn128F, Bn256>());
+        assert!(<SameFieldConverter as FieldConverter<Bn128F, Bn256>>::is_compatible());
+
+        let big = (BigUint::one() << 192) + (BigUint::one() << 64) + BigUint::from(3u8);
+        let elements = vec![
+            Element::<Bn128F>::zero(),
+            Element::one(),
+            Element::from(6u8),
+            Element::from(big),
+            Element::largest_element(),
+        ];
+        for n in &elements {
+            assert_eq!(
+                <SameFieldConverter as FieldConverter<Bn128F, Bn256>>::convert_field(n),
+                Bn256Converter::convert_field(n));
+        }
+    }
+
+    #[test]
+    fn limbs_from_biguint_respects_limb_count() {
+        let n = (BigUint::one() << 64) + BigUint::from(5u8);
+        assert_eq!(limbs_from_biguint::<1>(&BigUint::from(5u8)), Some([5]));
+        assert_eq!(limbs_from_biguint::<1>(&n), None);
+        assert_eq!(limbs_from_biguint::<2>(&n), Some([5, 1]));
+        assert_eq!(limbs_from_biguint::<4>(&n), Some([5, 1, 0, 0]));
+    }
+
+    #[test]
+    fn convert_element_bn256() {
+        let big = (BigUint::one() << 200) + BigUint::from(17u8);
+        for n in &[Element::<Bn128F>::zero(), Element::from(6u8), Element::from(big), Element::largest_element()] {
+            assert_eq!(convert_element::<Bn128F, Fr, BN256_LIMBS>(n), Some(Bn256Converter::convert_field(n)));
+        }
+        // A limb count which doesn't match the repr is rejected rather than panicking.
+        assert_eq!(convert_element::<Bn128F, Fr, 2>(&Element::from(6u8)), None);
+        assert_eq!(convert_element::<Bn128F, Fr, 5>(&Element::from(6u8)), None);
+    }
+
+    #[test]
+    fn convert_element_bls12_381() {
+        use pairing::bls12_381::Fr as BlsFr;
+        let big = (BigUint::one() << 250) + BigUint::from(17u8);
+        let elements = [Element::<r1cs::Bls12_381>::zero(), Element::from(6u8), Element::from(big),
+            Element::largest_element()];
+        for n in &elements {
+            let expected = fr_from_biguint::<BlsFr>(n.to_biguint());
+            assert!(expected.is_some());
+            assert_eq!(convert_element::<r1cs::Bls12_381, BlsFr, BLS12_381_LIMBS>(n), expected);
+        }
+    }
+
+    #[test]
+    fn roots_of_unity_keep_their_order() {
+        let omega_fr = <Fr as PrimeField>::root_of_unity();
+        let omega = Element::<Bn128F>::from(biguint_from_fr(&omega_fr));
+        assert_eq!(Bn256Converter::convert_field(&omega), omega_fr);
+
+        // omega has order 2^S in both fields.
+        let mut power_fr = Bn256Converter::convert_field(&omega);
+        let mut power = omega.clone();
+        for _ in 0..<Fr as PrimeField>::S {
+            power_fr.square();
+            power = power.clone() * power;
+        }
+        assert_eq!(power, Element::one());
+        assert_eq!(power_fr, Bn256Converter::convert_field(&Element::one()));
+
+        // Prove (omega x) * (omega^2 y) = omega^3 z, which holds for x * y = z only if the
+        // converted coefficients keep their multiplicative relationship.
+        let omega_2 = omega.clone() * omega.clone();
+        let omega_3 = omega_2.clone() * omega.clone();
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let z = builder.wire();
+        builder.assert_product(&scaled(x, omega), &scaled(y, omega_2), &scaled(z, omega_3));
+        let circuit: WrappedCircuit<Bn128F, Bn256, Bn256Converter> =
+            WrappedCircuit::new(builder.build(), product_witness(2, 3, 6), vec![z]);
+        assert_eq!(circuit.check_satisfied(), Ok(()));
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        let proof = prover.prove(product_witness(2, 3, 6), rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(6)]).unwrap());
+    }
+
+    #[test]
+    fn division_by_constant_converts_to_inverse() {
+        // x / 3 * 1 = z, where the r1cs coefficient 1 / 3 is stored as its reduced field element.
+        let third = Element::<Bn128F>::one() / Element::from(3u8);
+        assert_eq!(Bn256Converter::convert_field(&third), fr(3).inverse().unwrap());
+
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let z = builder.wire();
+        builder.assert_product(&scaled(x, third), &Expression::from(&Wire::ONE), &Expression::from(&z));
+        let mut witness_map = BTreeMap::new();
+        witness_map.insert(x.index, fr(6));
+        witness_map.insert(z.index, fr(2));
+        let circuit: WrappedCircuit<Bn128F, Bn256, Bn256Converter> =
+            WrappedCircuit::new(builder.build(), witness_map, vec![z]);
+        assert_eq!(circuit.check_satisfied(), Ok(()));
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        let proof = prover.prove(circuit.witness_map().clone(), rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(2)]).unwrap());
+        assert!(!prover.verify(&proof, &[fr(3)]).unwrap());
+    }
+
+    #[test]
+    fn many_term_c() {
+        // x * 1 = 1 w_1 + 2 w_2 + ... + n w_n, with every w_i one.
+        const N: usize = 200;
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let w = builder.wires(N);
+        let sum: BTreeMap<Wire, Element<Bn128F>> = w.iter().enumerate()
+            .map(|(i, wire)| (*wire, Element::from(BigUint::from(i + 1))))
+            .collect();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&Wire::ONE), &Expression::new(sum));
+        let mut witness_map: BTreeMap<u32, Fr> = w.iter().map(|wire| (wire.index, fr(1))).collect();
+        let total = Bn256Converter::convert_field(&Element::from(BigUint::from(N * (N + 1) / 2)));
+        witness_map.insert(x.index, total);
+        let circuit: WrappedCircuit<Bn128F, Bn256, Bn256Converter> =
+            WrappedCircuit::new(builder.build(), witness_map, vec![x]);
+        assert_eq!(circuit.check_satisfied(), Ok(()));
+
+        let mut cs = CollectingConstraintSystem::<Bn256>::new();
+        circuit.with_witness_map(circuit.witness_map().clone()).synthesize(&mut cs).unwrap();
+        assert_eq!(cs.constraints[0].2.as_ref().len(), N);
+        assert_eq!(cs.matrices(), circuit.to_matrices());
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        let proof = prover.prove(circuit.witness_map().clone(), rng).unwrap();
+        assert!(prover.verify(&proof, &[total]).unwrap());
+    }
+
+    #[test]
+    fn satisfaction_of_each_constraint() {
+        // x * y = z, x * x = y, y * y = z, z * 1 = 6.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let z = builder.wire();
+        let (x_exp, y_exp, z_exp) = (Expression::from(&x), Expression::from(&y), Expression::from(&z));
+        builder.assert_product(&x_exp, &y_exp, &z_exp);
+        builder.assert_product(&x_exp, &x_exp, &y_exp);
+        builder.assert_product(&y_exp, &y_exp, &z_exp);
+        let six = scaled(Wire::ONE, Element::from(6u8));
+        builder.assert_product(&z_exp, &Expression::from(&Wire::ONE), &six);
+        let circuit: WrappedCircuit<Bn128F, Bn256, Bn256Converter> =
+            WrappedCircuit::new(builder.build(), product_witness(2, 3, 6), vec![z]);
+        assert_eq!(circuit.satisfaction_map(), vec![true, false, false, true]);
+        assert_eq!(circuit.check_satisfied(), Err(1));
+        let satisfying = circuit.with_witness_map(product_witness(1, 1, 1));
+        assert_eq!(satisfying.satisfaction_map(), vec![true, true, true, false]);
+    }
+
+    #[test]
+    fn satisfaction_of_conditional_constraints() {
+        // x * y = z, and x * x = w when s is nonzero, whose index follows the gadget's constraint.
+        let circuit = conditional_circuit(conditional_witness(2, 3, 6, 5, 1));
+        assert_eq!(circuit.satisfaction_map(), vec![true, false]);
+        assert_eq!(circuit.check_satisfied(), Err(1));
+        let disabled = circuit.with_witness_map(conditional_witness(2, 3, 7, 5, 0));
+        assert_eq!(disabled.satisfaction_map(), vec![false, true]);
+    }
+
+    #[test]
+    fn zero_coefficients_are_skipped() {
+        // x * 1 = 0 s_0 + 0 s_1 + 5 s_2 + 0 s_3, a one-hot style row.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let s = builder.wires(4);
+        let row: BTreeMap<Wire, Element<Bn128F>> = s.iter().enumerate()
+            .map(|(i, wire)| (*wire, if i == 2 { Element::from(5u8) } else { Element::zero() }))
+            .collect();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&Wire::ONE), &Expression::new(row));
+        let mut witness_map = BTreeMap::new();
+        witness_map.insert(x.index, fr(5));
+        witness_map.insert(s[2].index, fr(1));
+        let circuit: WrappedCircuit<Bn128F, Bn256, Bn256Converter> =
+            WrappedCircuit::new(builder.build(), witness_map, vec![x]);
+
+        let mut cs = CollectingConstraintSystem::<Bn256>::new();
+        circuit.with_witness_map(circuit.witness_map().clone()).synthesize(&mut cs).unwrap();
+        let c = cs.constraints[0].2.as_ref();
+        assert_eq!(c.len(), 1);
+        assert_eq!(c[0].1, fr(5));
+        // Three terms in all, against the six of x, 1 and the whole row.
+        let terms: usize = cs.constraints.iter()
+            .map(|(a, b, c)| a.as_ref().len() + b.as_ref().len() + c.as_ref().len())
+            .sum();
+        assert_eq!(terms, 3);
+        assert_eq!(cs.matrices(), circuit.to_matrices());
+    }
+
+    #[test]
+    fn square_root_witness_and_coefficient() {
+        // y * y = x and y * 1 = r, for the root r of x which Fr's sqrt returns, with x public.
+        let mut x = Fr::from_str("12345678901234567890123456789").unwrap();
+        x.square();
+        let root = x.sqrt().unwrap();
+        let root_element: Element<Bn128F> = element_from_fr(&root).unwrap();
+        let x_element: Element<Bn128F> = element_from_fr(&x).unwrap();
+        assert_eq!(Bn256Converter::convert_field(&root_element), root);
+        assert_eq!(root_element.clone() * root_element.clone(), x_element);
+
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x_wire = builder.wire();
+        let y = builder.wire();
+        builder.assert_product(&Expression::from(&y), &Expression::from(&y), &Expression::from(&x_wire));
+        let one = Expression::from(&Wire::ONE);
+        builder.assert_product(&Expression::from(&y), &one, &scaled(Wire::ONE, root_element));
+        let circuit: WrappedCircuit<Bn128F, Bn256, Bn256Converter> =
+            WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![x_wire]);
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        let mut wrong = root;
+        wrong.add_assign(&Fr::one());
+        let mut negated = root;
+        negated.negate();
+        // The negated root also squares to x, but isn't the root the second constraint fixes.
+        for &(y_value, satisfied) in &[(root, true), (wrong, false), (negated, false)] {
+            let mut witness_map = BTreeMap::new();
+            witness_map.insert(x_wire.index, x);
+            witness_map.insert(y.index, y_value);
+            let circuit = circuit.with_witness_map(witness_map);
+            assert_eq!(circuit.check_satisfied().is_ok(), satisfied);
+            let proof = prover.prove(circuit.witness_map().clone(), rng).unwrap();
+            assert_eq!(prover.verify(&proof, &[x]).unwrap(), satisfied);
+        }
+    }
+
+    #[test]
+    fn constant_wire_maps_to_one() {
+        // x * 1 = z, with the constant wire mistakenly listed as a public input.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let z = builder.wire();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&Wire::ONE), &Expression::from(&z));
+        let mut witness_map = BTreeMap::new();
+        witness_map.insert(x.index, fr(6));
+        witness_map.insert(z.index, fr(6));
+        let circuit: WrappedCircuit<Bn128F, Bn256, Bn256Converter> =
+            WrappedCircuit::new(builder.build(), witness_map, vec![Wire::ONE, z]);
+
+        assert_eq!(circuit.warnings(), &[Warning::ConstantWireInPublicInputs]);
+        assert_eq!(circuit.public_inputs(), &vec![z]);
+        assert_eq!(circuit.public_input_order(), vec![z]);
+        assert_eq!(circuit.public_input_values(), vec![fr(6)]);
+        assert_eq!(circuit.check_satisfied(), Ok(()));
+
+        // Only bellman's own one and z are inputs, and x is the only auxiliary variable.
+        let mut cs = CollectingConstraintSystem::<Bn256>::new();
+        circuit.with_witness_map(circuit.witness_map().clone()).synthesize(&mut cs).unwrap();
+        assert_eq!((cs.inputs.len(), cs.aux.len()), (2, 1));
+        assert_eq!(cs.matrices(), circuit.to_matrices());
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        let proof = prover.prove(circuit.witness_map().clone(), rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(6)]).unwrap());
+    }
+
+    #[test]
+    fn public_inputs_match_verifier() {
+        // x * y = z and z * 1 = z + w, so that w must be zero, with z and w public.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let w = builder.wires(4);
+        builder.assert_product(&Expression::from(&w[0]), &Expression::from(&w[1]), &Expression::from(&w[2]));
+        let z_plus_w = Expression::from(&w[2]) + Expression::from(&w[3]);
+        builder.assert_product(&Expression::from(&w[2]), &Expression::from(&Wire::ONE), &z_plus_w);
+        let mut witness_map = BTreeMap::new();
+        for (wire, n) in w.iter().zip(&[2u8, 3, 6, 0]) {
+            witness_map.insert(wire.index, fr(*n));
+        }
+        let circuit: WrappedCircuit<Bn128F, Bn256, Bn256Converter> =
+            WrappedCircuit::new(builder.build(), witness_map, vec![w[2], w[3]]);
+
+        assert_eq!(circuit.assert_public_inputs_match(&[fr(6), fr(0)]), Ok(()));
+        assert_eq!(circuit.assert_public_inputs_match(&[fr(6), fr(1)]), Err(1));
+        assert_eq!(circuit.assert_public_inputs_match(&[fr(5), fr(0)]), Err(0));
+        assert_eq!(circuit.assert_public_inputs_match(&[fr(6)]), Err(1));
+
+        // Once w is allocated before z, the verifier supplies w first.
+        let reordered = circuit.with_public_input_order(vec![w[3], w[2]]).unwrap();
+        assert_eq!(reordered.assert_public_inputs_match(&[fr(0), fr(6)]), Ok(()));
+        assert_eq!(reordered.assert_public_inputs_match(&[fr(6), fr(0)]), Err(0));
+    }
+
+    #[test]
+    fn prove_bit_decomposition() {
+        // The constraints of a bit decomposition gadget: each bit b_i is boolean, which r1cs
+        // expresses as b_i * (b_i - 1) = 0 with a coefficient of -1 on the constant wire, and
+        // (sum of 2^i b_i) * 1 = x, with x public.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let bits = builder.wires(8);
+        let mut recomposition = Expression::zero();
+        for (i, bit) in bits.iter().enumerate() {
+            builder.assert_boolean(&Expression::from(bit));
+            recomposition = recomposition + scaled(*bit, Element::from(1u8 << i));
+        }
+        builder.assert_product(&recomposition, &Expression::from(&Wire::ONE), &Expression::from(&x));
+        let circuit: WrappedCircuit<Bn128F, Bn256, Bn256Converter> =
+            WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![x]);
+        assert_eq!(circuit.gadget().constraints.len(), 9);
+
+        let decomposition = |value: u8, bit_values: [u8; 8]| {
+            let mut witness_map = BTreeMap::new();
+            witness_map.insert(x.index, fr(value));
+            for (bit, b) in bits.iter().zip(&bit_values) {
+                witness_map.insert(bit.index, fr(*b));
+            }
+            witness_map
+        };
+        // 181 is 0b10110101.
+        let witness_map = decomposition(181, [1, 0, 1, 0, 1, 1, 0, 1]);
+        assert_eq!(circuit.with_witness_map(witness_map.clone()).check_satisfied(), Ok(()));
+        // Doubling the least significant bit keeps the sum, but the bit isn't boolean.
+        let non_boolean = decomposition(182, [2, 0, 1, 0, 1, 1, 0, 1]);
+        assert_eq!(circuit.with_witness_map(non_boolean).check_satisfied(), Err(0));
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        let proof = prover.prove(witness_map, rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(181)]).unwrap());
+        assert!(!prover.verify(&proof, &[fr(180)]).unwrap());
+    }
+
+    #[test]
+    fn evaluators_agree_for_same_field() {
+        for &(x, y, z) in &[(2u8, 3u8, 6u8), (2, 3, 7)] {
+            let circuit = product_circuit(product_witness(x, y, z));
+            let mut values = WireValues::<Bn128F>::new();
+            values.set(Wire { index: 1 }, Element::from(x));
+            values.set(Wire { index: 2 }, Element::from(y));
+            values.set(Wire { index: 3 }, Element::from(z));
+            assert_eq!(circuit.check_satisfied(), circuit.check_satisfied_in_field(&values));
+        }
+        assert_eq!(product_circuit(product_witness(2, 3, 7)).check_satisfied(), Err(0));
+    }
+
+    #[test]
+    fn evaluators_agree_on_conditional_constraints() {
+        // x * y = z, and x * x = w when s is nonzero.
+        for &(w, s, expected) in &[(4u8, 1u8, Ok(())), (5, 0, Ok(())), (5, 1, Err(1))] {
+            let circuit = conditional_circuit(conditional_witness(2, 3, 6, w, s));
+            let mut values = WireValues::<Bn128F>::new();
+            for (index, n) in (1..).zip(&[2u8, 3, 6, w, s]) {
+                values.set(Wire { index }, Element::from(*n));
+            }
+            assert_eq!(circuit.check_satisfied_in_field(&values), expected);
+            assert_eq!(circuit.check_satisfied(), expected);
+        }
+    }
+
     /*#[test]
     fn invalid_proof() {
         let rng = &mut thread_rng();
@@ -249,12 +1176,7 @@ mod tests {
         let z = builder.wire();
         builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
         let gadget = builder.build();
-        WrappedCircuit {
-            gadget,
-            witness_map,
-            public_inputs: vec![z],
-            _c: PhantomData
-        }
+        WrappedCircuit::new(gadget, witness_map, vec![z])
     }
 
 
@@ -0,0 +1,96 @@
+use std::fs;
+use std::path::Path;
+use r1cs::Field;
+use serde_json::{json, Value};
+
+use crate::{Error, FieldConverter, TaggedEngine, WrappedCircuit};
+use crate::audit::to_hex;
+
+/// Writes a JSON manifest describing the circuit's contract, to ship alongside its parameters:
+/// its fingerprint, its engine, the wire of each public input in the order verifiers supply them,
+/// the number of constraints `synthesize` enforces, and the version of this crate.
+pub fn write_manifest<F, E, C, P>(circuit: &WrappedCircuit<F, E, C>, path: P) -> Result<(), Error>
+where
+    F: Field,
+    E: TaggedEngine,
+    C: FieldConverter<F, E>,
+    P: AsRef<Path>,
+{
+    fs::write(path, manifest(circuit).to_string())?;
+    Ok(())
+}
+
+/// Checks that the circuit matches the manifest at `path`, failing with `ManifestMismatch` naming
+/// every field which differs. The crate version is informational, and isn't compared.
+pub fn verify_manifest<F, E, C, P>(circuit: &WrappedCircuit<F, E, C>, path: P) -> Result<(), Error>
+where
+    F: Field,
+    E: TaggedEngine,
+    C: FieldConverter<F, E>,
+    P: AsRef<Path>,
+{
+    let contents = fs::read_to_string(path)?;
+    let loaded: Value = serde_json::from_str(&contents)
+        .map_err(|e| Error::InvalidFormat(e.to_string()))?;
+    let expected = manifest(circuit);
+    let mismatched: Vec<String> = ["fingerprint", "engine", "public_inputs", "num_constraints"].iter()
+        .filter(|field| loaded[**field] != expected[**field])
+        .map(|field| field.to_string())
+        .collect();
+    if mismatched.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::ManifestMismatch(mismatched))
+    }
+}
+
+fn manifest<F, E, C>(circuit: &WrappedCircuit<F, E, C>) -> Value
+where
+    F: Field,
+    E: TaggedEngine,
+    C: FieldConverter<F, E>,
+{
+    let public_inputs: Vec<u32> = circuit.public_input_order().iter()
+        .map(|wire| wire.index)
+        .collect();
+    let num_constraints = circuit.gadget().constraints.len()
+        + circuit.raw_constraints().len()
+        + 2 * circuit.conditional_constraints().len();
+    json!({
+        "fingerprint": to_hex(&circuit.fingerprint()),
+        "engine": E::CURVE.to_string(),
+        "public_inputs": public_inputs,
+        "num_constraints": num_constraints,
+        "crate_version": env!("CARGO_PKG_VERSION"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+
+    use crate::{verify_manifest, write_manifest, Error};
+    use crate::test_util::product_circuit;
+
+    #[test]
+    fn manifest_round_trip() {
+        let dir = std::env::temp_dir().join(format!("r1cs-bellman-manifest-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("manifest.json");
+
+        let circuit = product_circuit(BTreeMap::new());
+        write_manifest(&circuit, &path).unwrap();
+        assert!(verify_manifest(&circuit, &path).is_ok());
+
+        let (mismatching, _) = product_circuit(BTreeMap::new()).with_context_input();
+        match verify_manifest(&mismatching, &path) {
+            Err(Error::ManifestMismatch(fields)) => {
+                assert_eq!(fields, vec!["fingerprint", "public_inputs", "num_constraints"]);
+            }
+            _ => panic!("expected a manifest mismatch"),
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::{BTreeMap, HashMap};
+use bellman::Index;
+use pairing::Engine;
+use pairing::ff::{Field as _, PrimeField};
+use r1cs::{Expression, Field, Wire};
+
+use crate::{FieldConverter, WrappedCircuit};
+
+/// A constraint system `A z * B z = C z` as sparse matrices, one row per constraint. Column 0 is
+/// the constant one, followed by the public inputs and then the private wires, each in allocation
+/// order; this is how bellman orders its variables.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct R1csMatrices<Fr: PrimeField> {
+    pub num_inputs: usize,
+    pub num_aux: usize,
+    pub a: Vec<BTreeMap<usize, Fr>>,
+    pub b: Vec<BTreeMap<usize, Fr>>,
+    pub c: Vec<BTreeMap<usize, Fr>>,
+}
+
+impl<Fr: PrimeField> R1csMatrices<Fr> {
+    /// Creates matrices with no rows, over `num_inputs` input columns (including the constant one)
+    /// and `num_aux` auxiliary columns.
+    pub fn new(num_inputs: usize, num_aux: usize) -> Self {
+        Self { num_inputs, num_aux, a: Vec::new(), b: Vec::new(), c: Vec::new() }
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.a.len()
+    }
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Converts the circuit into matrices describing the constraint system `synthesize` enforces,
+    /// including any raw and conditional constraints.
+    pub fn to_matrices(&self) -> R1csMatrices<E::Fr> {
+        let single = |column: usize| -> BTreeMap<usize, E::Fr> {
+            let mut row = BTreeMap::new();
+            row.insert(column, E::Fr::one());
+            row
+        };
+        let indices = self.variable_indices();
+        let num_inputs = 1 + indices.iter().filter(|(_, index)| matches!(index, Index::Input(_))).count();
+        let column = |index: Index| match index {
+            Index::Input(i) => i,
+            Index::Aux(i) => num_inputs + i,
+        };
+        let mut columns: HashMap<Wire, usize> = indices.iter()
+            .map(|(wire, index)| (*wire, column(*index)))
+            .collect();
+        columns.insert(Wire::ONE, 0);
+        let row = |exp: &Expression<F>| -> BTreeMap<usize, E::Fr> {
+            exp.coefficients().iter()
+                .map(|(wire, coeff)| (columns[wire], C::convert_field(coeff)))
+                .filter(|(_, coeff)| !coeff.is_zero())
+                .collect()
+        };
+
+        let mut matrices = R1csMatrices::new(num_inputs, indices.len() + 1 - num_inputs);
+        for constraint in &self.gadget().constraints {
+            matrices.a.push(row(&constraint.a));
+            matrices.b.push(row(&constraint.b));
+            matrices.c.push(row(&constraint.c));
+        }
+        for raw in self.raw_constraints() {
+            for (lc, rows) in vec![(&raw.a, &mut matrices.a), (&raw.b, &mut matrices.b), (&raw.c, &mut matrices.c)] {
+                let mut row = BTreeMap::new();
+                for (variable, coeff) in lc.as_ref() {
+                    row.entry(column(variable.get_unchecked()))
+                        .or_insert_with(E::Fr::zero)
+                        .add_assign(coeff);
+                }
+                row.retain(|_, coeff: &mut E::Fr| !coeff.is_zero());
+                rows.push(row);
+            }
+        }
+        // Each conditional constraint's product variable is allocated after all of the wires.
+        let num_wire_aux = indices.len() + 1 - num_inputs;
+        matrices.num_aux += self.conditional_constraints().len();
+        for (j, conditional) in self.conditional_constraints().iter().enumerate() {
+            let t = num_inputs + num_wire_aux + j;
+            matrices.a.push(row(&conditional.constraint.a));
+            matrices.b.push(row(&conditional.constraint.b));
+            matrices.c.push(single(t));
+            matrices.a.push(single(columns[&conditional.selector]));
+            let mut b = single(t);
+            for (column, coeff) in row(&conditional.constraint.c) {
+                let mut negated = coeff;
+                negated.negate();
+                b.insert(column, negated);
+            }
+            matrices.b.push(b);
+            matrices.c.push(BTreeMap::new());
+        }
+        matrices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use bellman::{Circuit, LinearCombination};
+    use pairing::compact_bn256::{Bn256, Fr};
+    use pairing::ff::Field as _;
+    use r1cs::{Expression, GadgetBuilder, Wire, Bn128 as Bn128F};
+
+    use crate::{one_variable, CollectingConstraintSystem, RawConstraint, WrappedCircuit};
+    use crate::test_util::{
+        conditional_circuit, conditional_witness, product_circuit, product_witness, ProductCircuit,
+    };
+
+    fn assert_matches_bellman(circuit: ProductCircuit) {
+        let expected = circuit.to_matrices();
+        let mut cs = CollectingConstraintSystem::<Bn256>::new();
+        circuit.synthesize(&mut cs).unwrap();
+        assert_eq!(cs.matrices(), expected);
+    }
+
+    #[test]
+    fn product_circuit_matrices_match_bellman() {
+        let circuit = product_circuit(product_witness(2, 3, 6));
+        let matrices = circuit.to_matrices();
+        assert_eq!((matrices.num_inputs, matrices.num_aux, matrices.num_constraints()), (2, 2, 1));
+        assert_matches_bellman(circuit);
+    }
+
+    #[test]
+    fn interleaved_public_inputs_match_bellman() {
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let w = builder.wires(4);
+        builder.assert_product(&Expression::from(&w[0]), &Expression::from(&w[1]), &Expression::from(&w[2]));
+        builder.assert_product(&Expression::from(&w[2]), &(Expression::from(&w[3]) + Expression::from(&w[0])),
+                               &Expression::from(&w[1]));
+        let circuit: ProductCircuit = WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![w[1], w[3]]);
+        assert_matches_bellman(circuit);
+    }
+
+    #[test]
+    fn raw_and_conditional_constraints_match_bellman() {
+        // x * y = z, x * x = w when s is nonzero, and the raw constraint (y + y) * 1 = z.
+        let mut circuit = conditional_circuit(conditional_witness(2, 3, 6, 4, 1));
+        let y = circuit.variable_of(Wire { index: 2 }).unwrap();
+        let z = circuit.variable_of(Wire { index: 3 }).unwrap();
+        circuit.add_raw_constraint(RawConstraint {
+            a: LinearCombination::zero() + (Fr::one(), y) + (Fr::one(), y),
+            b: LinearCombination::zero() + (Fr::one(), one_variable()),
+            c: LinearCombination::zero() + (Fr::one(), z),
+        });
+        let matrices = circuit.to_matrices();
+        assert_eq!((matrices.num_inputs, matrices.num_aux, matrices.num_constraints()), (2, 5, 4));
+        assert_matches_bellman(circuit);
+    }
+}
@@ -0,0 +1,109 @@
+use std::mem::size_of;
+use bellman::Variable;
+use pairing::Engine;
+use r1cs::{Constraint, Element, Field, Wire};
+
+use crate::{FieldConverter, WrappedCircuit};
+
+/// The approximate bookkeeping cost of one `BTreeMap` entry beyond its key and value, amortized
+/// over a node's capacity.
+const BTREE_ENTRY_OVERHEAD: usize = 2 * size_of::<usize>();
+
+/// An estimate, in bytes, of the memory a circuit occupies, and of what `synthesize` will allocate
+/// on top of it. The constraint system's own storage, such as bellman's prover assignments, is not
+/// included.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryEstimate {
+    /// The gadget's constraints, including their coefficients' heap storage.
+    pub constraints: usize,
+    /// The witness map.
+    pub witness_map: usize,
+    /// The map from wires to bellman variables which `synthesize` builds.
+    pub variable_cache: usize,
+}
+
+impl MemoryEstimate {
+    pub fn total(&self) -> usize {
+        self.constraints + self.witness_map + self.variable_cache
+    }
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Estimates the circuit's memory usage from the sizes of its types and its element counts,
+    /// without allocating anything.
+    pub fn memory_estimate(&self) -> MemoryEstimate {
+        let term = size_of::<Wire>() + size_of::<Element<F>>() + BTREE_ENTRY_OVERHEAD;
+        let constraints: usize = self.gadget().constraints.iter()
+            .map(|constraint| {
+                let Constraint { a, b, c } = constraint;
+                let terms: usize = [a, b, c].iter()
+                    .map(|exp| exp.coefficients().values()
+                        .map(|coeff| term + limb_bytes(coeff))
+                        .sum::<usize>())
+                    .sum();
+                size_of::<Constraint<F>>() + terms
+            })
+            .sum();
+        let witness_map = self.witness_map().len()
+            * (size_of::<u32>() + size_of::<E::Fr>() + BTREE_ENTRY_OVERHEAD);
+        let variable_cache = self.referenced_wires().len()
+            * (size_of::<Wire>() + size_of::<Variable>() + BTREE_ENTRY_OVERHEAD);
+        MemoryEstimate { constraints, witness_map, variable_cache }
+    }
+}
+
+/// The heap storage of a coefficient's integer, in whole 64-bit limbs.
+fn limb_bytes<F: Field>(coeff: &Element<F>) -> usize {
+    (coeff.to_biguint().bits() + 63) / 64 * 8
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::mem::size_of;
+    use pairing::compact_bn256::Fr;
+    use r1cs::{Expression, GadgetBuilder, Wire, Bn128 as Bn128F};
+
+    use crate::WrappedCircuit;
+    use crate::test_util::{fr, ProductCircuit};
+
+    /// Builds a chain of `n` squarings, with a witness for every wire.
+    fn squaring_chain(n: usize) -> ProductCircuit {
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let mut wire = builder.wire();
+        let mut witness_map = BTreeMap::new();
+        witness_map.insert(wire.index, fr(1));
+        for _ in 0..n {
+            let next = builder.wire();
+            let exp = Expression::from(&wire);
+            builder.assert_product(&exp, &exp, &Expression::from(&next));
+            witness_map.insert(next.index, fr(1));
+            wire = next;
+        }
+        WrappedCircuit::new(builder.build(), witness_map, vec![wire])
+    }
+
+    #[test]
+    fn estimate_grows_with_constraints() {
+        let estimates: Vec<_> = [1, 10, 100].iter()
+            .map(|n| squaring_chain(*n).memory_estimate())
+            .collect();
+        for pair in estimates.windows(2) {
+            assert!(pair[1].constraints > pair[0].constraints);
+            assert!(pair[1].witness_map > pair[0].witness_map);
+            assert!(pair[1].variable_cache > pair[0].variable_cache);
+        }
+
+        // The payload alone is a lower bound on the real usage, and the overhead shouldn't be
+        // more than a few times that.
+        let circuit = squaring_chain(100);
+        let estimate = circuit.memory_estimate();
+        let witness_payload = circuit.witness_map().len() * (size_of::<u32>() + size_of::<Fr>());
+        assert!(estimate.witness_map >= witness_payload);
+        assert!(estimate.witness_map <= 4 * witness_payload);
+        let terms = 3 * circuit.gadget().constraints.len();
+        let constraint_payload = terms * (size_of::<Wire>() + 8);
+        assert!(estimate.constraints >= constraint_payload);
+        assert!(estimate.total() <= 16 * (constraint_payload + witness_payload));
+    }
+}
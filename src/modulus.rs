@@ -0,0 +1,113 @@
+use num::BigUint;
+use pairing::Engine;
+use r1cs::{Element, Field};
+
+use crate::{engine_modulus, Error, FieldConverter, WrappedCircuit};
+
+/// Validates that elements of an r1cs field fit in an engine's scalar field, so that converting
+/// them doesn't silently wrap around.
+#[derive(Clone, Debug)]
+pub struct ModulusCheck {
+    modulus: BigUint,
+}
+
+impl ModulusCheck {
+    /// Checks against the modulus `E::Fr` reports.
+    pub fn for_engine<E: Engine>() -> Self {
+        Self::with_modulus(engine_modulus::<E>())
+    }
+
+    /// Checks against a modulus supplied by the caller, for engines which don't expose theirs
+    /// conveniently.
+    pub fn with_modulus(modulus: BigUint) -> Self {
+        Self { modulus }
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.modulus
+    }
+
+    pub fn check<F: Field>(&self, n: &Element<F>) -> Result<(), Error> {
+        if n.to_biguint() < &self.modulus {
+            Ok(())
+        } else {
+            Err(Error::ElementOutOfRange {
+                value: n.to_biguint().clone(),
+                modulus: self.modulus.clone(),
+            })
+        }
+    }
+
+    /// Converts `n` with the converter `C`, after checking that it fits.
+    pub fn convert<F: Field, E: Engine, C: FieldConverter<F, E>>(
+        &self,
+        n: &Element<F>
+    ) -> Result<E::Fr, Error> {
+        self.check(n)?;
+        Ok(C::convert_field(n))
+    }
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Checks that every coefficient in the circuit's constraints, including its conditional
+    /// constraints, fits the modulus of `check`.
+    pub fn check_coefficients(&self, check: &ModulusCheck) -> Result<(), Error> {
+        let conditionals = self.conditional_constraints().iter().map(|conditional| &conditional.constraint);
+        for constraint in self.gadget().constraints.iter().chain(conditionals) {
+            for exp in &[&constraint.a, &constraint.b, &constraint.c] {
+                for coeff in exp.coefficients().values() {
+                    check.check(coeff)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use num::BigUint;
+    use pairing::compact_bn256::Bn256;
+    use r1cs::{Element, Field, Wire, Bn128 as Bn128F};
+
+    use crate::{Bn256Converter, Error, ModulusCheck};
+    use crate::test_util::{conditional_circuit, fr, product_circuit, scaled};
+
+    #[test]
+    fn default_modulus_is_engine_modulus() {
+        assert_eq!(ModulusCheck::for_engine::<Bn256>().modulus(), &Bn128F::order());
+    }
+
+    #[test]
+    fn overflow_check_uses_supplied_modulus() {
+        let check = ModulusCheck::with_modulus(BigUint::from(5u8));
+        let four = Element::<Bn128F>::from(4u8);
+        let six = Element::<Bn128F>::from(6u8);
+
+        assert_eq!(check.convert::<Bn128F, Bn256, Bn256Converter>(&four).unwrap(), fr(4));
+        match check.convert::<Bn128F, Bn256, Bn256Converter>(&six) {
+            Err(Error::ElementOutOfRange { value, modulus }) => {
+                assert_eq!(value, BigUint::from(6u8));
+                assert_eq!(modulus, BigUint::from(5u8));
+            }
+            _ => panic!("expected an out of range error"),
+        }
+
+        // The product circuit's coefficients are all one, which fits either modulus.
+        assert!(product_circuit(BTreeMap::new()).check_coefficients(&check).is_ok());
+    }
+
+    #[test]
+    fn conditional_coefficients_are_checked() {
+        // x * y = z, and x * x = 6 w when s is nonzero.
+        let mut circuit = conditional_circuit(BTreeMap::new());
+        let check = ModulusCheck::with_modulus(BigUint::from(5u8));
+        assert!(circuit.check_coefficients(&check).is_ok());
+        circuit.conditional_constraints[0].constraint.c = scaled(Wire { index: 4 }, Element::from(6u8));
+        match circuit.check_coefficients(&check) {
+            Err(Error::ElementOutOfRange { value, .. }) => assert_eq!(value, BigUint::from(6u8)),
+            _ => panic!("expected an out of range error"),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Strategies for ordering a circuit's wires when allocating them as bellman variables.
+//!
+//! The allocation order determines which column of the constraint system each wire occupies, and
+//! the order public input values must be given in when verifying. Parameters are only valid for
+//! the order they were generated with, so changing a circuit's strategy requires a new setup;
+//! the strategy is covered by the circuit's fingerprint.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use pairing::Engine;
+use r1cs::{Field, Wire};
+
+use crate::{Error, FieldConverter, WrappedCircuit};
+
+pub trait WireOrdering {
+    /// Returns the order in which to allocate `wires`, which are given in the order the
+    /// constraints first reference them. `public_inputs` is the circuit's declared public inputs,
+    /// in declaration order. The result must be a permutation of `wires`.
+    fn order(&self, wires: &[Wire], public_inputs: &[Wire]) -> Vec<Wire>;
+}
+
+/// Allocates wires in the order the constraints first reference them. This is the default. Adding
+/// a constraint anywhere but at the end may shift the columns of later wires.
+pub struct FirstUseOrder;
+
+impl WireOrdering for FirstUseOrder {
+    fn order(&self, wires: &[Wire], _public_inputs: &[Wire]) -> Vec<Wire> {
+        wires.to_vec()
+    }
+}
+
+/// Allocates wires in order of their index. The layout then depends only on which wires are used,
+/// not on the order of the constraints, and public inputs are verified in index order.
+pub struct SortedByIndex;
+
+impl WireOrdering for SortedByIndex {
+    fn order(&self, wires: &[Wire], _public_inputs: &[Wire]) -> Vec<Wire> {
+        let mut order = wires.to_vec();
+        order.sort();
+        order
+    }
+}
+
+/// Allocates the public inputs first, in their declared order, followed by the private wires in
+/// first-use order. Public inputs are then verified in the order they were declared.
+pub struct PublicFirst;
+
+impl WireOrdering for PublicFirst {
+    fn order(&self, wires: &[Wire], public_inputs: &[Wire]) -> Vec<Wire> {
+        let used: HashSet<&Wire> = wires.iter().collect();
+        let public: HashSet<&Wire> = public_inputs.iter().collect();
+        let mut order: Vec<Wire> = public_inputs.iter().filter(|wire| used.contains(wire)).cloned().collect();
+        order.extend(wires.iter().filter(|wire| !public.contains(wire)));
+        order
+    }
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Allocates the public inputs in `order`, say to match an external verifier's fixed layout,
+    /// and so verifies them in that order too. This replaces the wire ordering with `PublicFirst`.
+    /// `order` must be a permutation of the declared public inputs.
+    pub fn set_public_input_order(&mut self, order: Vec<Wire>) -> Result<(), Error> {
+        let declared: HashSet<&Wire> = self.public_inputs().iter().collect();
+        let given: HashSet<&Wire> = order.iter().collect();
+        if order.len() != self.public_inputs().len() || given != declared {
+            return Err(Error::InvalidPublicInputOrder);
+        }
+        self.public_inputs = order;
+        self.wire_ordering = Arc::new(PublicFirst);
+        Ok(())
+    }
+
+    pub fn with_public_input_order(mut self, order: Vec<Wire>) -> Result<Self, Error> {
+        self.set_public_input_order(order)?;
+        Ok(self)
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use bellman::Index::Input;
+    use r1cs::{Expression, GadgetBuilder, Wire, Bn128 as Bn128F};
+    use rand::thread_rng;
+
+    use crate::{FirstUseOrder, Groth16Prover, PublicFirst, SortedByIndex, WrappedCircuit};
+    use crate::test_util::{fr, ProductCircuit};
+
+    /// Builds a circuit asserting `w2 * w1 = w3` and `w3 * w1 = w4`, listing the second
+    /// constraint first, with public inputs `[w2, w4]`.
+    fn circuit(witness_map: BTreeMap<u32, pairing::compact_bn256::Fr>) -> ProductCircuit {
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let w = builder.wires(4);
+        builder.assert_product(&Expression::from(&w[2]), &Expression::from(&w[0]), &Expression::from(&w[3]));
+        builder.assert_product(&Expression::from(&w[1]), &Expression::from(&w[0]), &Expression::from(&w[2]));
+        WrappedCircuit::new(builder.build(), witness_map, vec![w[1], w[3]])
+    }
+
+    fn witness() -> BTreeMap<u32, pairing::compact_bn256::Fr> {
+        vec![(1, fr(2)), (2, fr(3)), (3, fr(6)), (4, fr(12))].into_iter().collect()
+    }
+
+    #[test]
+    fn strategies_are_distinct_and_valid() {
+        let wires = |indices: &[u32]| -> Vec<Wire> { indices.iter().map(|&index| Wire { index }).collect() };
+        let circuits = vec![
+            (circuit(witness()).with_wire_ordering(FirstUseOrder), wires(&[4, 2])),
+            (circuit(witness()).with_wire_ordering(SortedByIndex), wires(&[2, 4])),
+            (circuit(witness()).with_wire_ordering(PublicFirst), wires(&[2, 4])),
+        ];
+        assert_eq!(circuits[0].0.allocation_order(), wires(&[3, 1, 4, 2]));
+        assert_eq!(circuits[1].0.allocation_order(), wires(&[1, 2, 3, 4]));
+        assert_eq!(circuits[2].0.allocation_order(), wires(&[2, 4, 3, 1]));
+
+        let rng = &mut thread_rng();
+        for (i, (circuit, public_order)) in circuits.iter().enumerate() {
+            assert_eq!(&circuit.public_input_order(), public_order);
+            for (other, _) in &circuits[i + 1..] {
+                assert_ne!(circuit.to_matrices(), other.to_matrices());
+                assert_ne!(circuit.fingerprint(), other.fingerprint());
+            }
+
+            let prover = Groth16Prover::setup(circuit, rng).unwrap();
+            let proof = prover.prove(witness(), rng).unwrap();
+            assert!(prover.verify(&proof, &circuit.public_input_values()).unwrap());
+        }
+    }
+
+    #[test]
+    fn custom_public_input_order() {
+        let w = |index| Wire { index };
+        let circuit = circuit(witness()).with_public_input_order(vec![w(4), w(2)]).unwrap();
+        assert_eq!(circuit.public_input_order(), vec![w(4), w(2)]);
+        assert_eq!(&circuit.variable_indices()[..2], &[(w(4), Input(1)), (w(2), Input(2))]);
+        assert_eq!(circuit.public_input_values(), vec![fr(12), fr(3)]);
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        let proof = prover.prove(witness(), rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(12), fr(3)]).unwrap());
+        assert!(!prover.verify(&proof, &[fr(3), fr(12)]).unwrap());
+
+        for order in vec![vec![w(4)], vec![w(4), w(4)], vec![w(4), w(1)], vec![w(2), w(4), w(1)]] {
+            assert!(circuit.with_witness_map(witness()).with_public_input_order(order).is_err());
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! An experimental conversion of circuits into Plonk-style arithmetic gates, enabled by the `plonk`
+//! feature.
+
+use std::collections::BTreeMap;
+use pairing::Engine;
+use r1cs::{Element, Expression, Field, Wire, WireValues};
+
+use crate::{FieldConverter, WrappedCircuit};
+
+/// An arithmetic gate `q_m a b + q_l a + q_r b + q_o c + q_c = 0`, whose `a`, `b` and `c` are
+/// indices into the variables of a `PlonkCircuit`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlonkGate<F: Field> {
+    pub a: usize,
+    pub b: usize,
+    pub c: usize,
+    pub q_m: Element<F>,
+    pub q_l: Element<F>,
+    pub q_r: Element<F>,
+    pub q_o: Element<F>,
+    pub q_c: Element<F>,
+}
+
+impl<F: Field> PlonkGate<F> {
+    fn evaluate(&self, assignment: &[Element<F>]) -> Element<F> {
+        let (a, b, c) = (&assignment[self.a], &assignment[self.b], &assignment[self.c]);
+        self.q_m.clone() * a.clone() * b.clone()
+            + self.q_l.clone() * a.clone()
+            + self.q_r.clone() * b.clone()
+            + self.q_o.clone() * c.clone()
+            + self.q_c.clone()
+    }
+}
+
+/// A circuit expressed as Plonk-style arithmetic gates. Variable 0 is the constant one, the next
+/// are the circuit's wires, and the rest are intermediate variables introduced by the conversion.
+#[derive(Clone, Debug)]
+pub struct PlonkCircuit<F: Field> {
+    gates: Vec<PlonkGate<F>>,
+    wires: Vec<Wire>,
+    num_variables: usize,
+    public_inputs: Vec<usize>,
+}
+
+impl<F: Field> PlonkCircuit<F> {
+    pub fn gates(&self) -> &[PlonkGate<F>] {
+        &self.gates
+    }
+
+    /// Returns the wire of each variable after the constant one which stands for a wire.
+    pub fn wires(&self) -> &[Wire] {
+        &self.wires
+    }
+
+    pub fn num_variables(&self) -> usize {
+        self.num_variables
+    }
+
+    /// Returns the variables of the public inputs, in the order a verifier supplies them.
+    pub fn public_inputs(&self) -> &[usize] {
+        &self.public_inputs
+    }
+
+    /// Computes every variable's value from the values of the circuit's wires, which must assign
+    /// every wire the constraints reference.
+    pub fn assignment(&self, values: &WireValues<F>) -> Vec<Element<F>> {
+        let mut assignment: Vec<Option<Element<F>>> = vec![None; self.num_variables];
+        assignment[0] = Some(Element::one());
+        for (i, wire) in self.wires.iter().enumerate() {
+            assignment[i + 1] = Some(values.get(*wire).clone());
+        }
+        // Each intermediate variable is first the output of the gate defining it, whose q_o is -1.
+        for gate in &self.gates {
+            if assignment[gate.c].is_none() {
+                let value = |variable: usize| assignment[variable].clone()
+                    .expect("intermediate variable used before it is defined");
+                let (a, b) = (value(gate.a), value(gate.b));
+                assignment[gate.c] = Some(gate.q_m.clone() * a.clone() * b.clone()
+                    + gate.q_l.clone() * a
+                    + gate.q_r.clone() * b
+                    + gate.q_c.clone());
+            }
+        }
+        assignment.into_iter().map(Option::unwrap).collect()
+    }
+
+    /// Returns whether `assignment` satisfies every gate.
+    pub fn is_satisfied(&self, assignment: &[Element<F>]) -> bool {
+        self.gates.iter().all(|gate| gate.evaluate(assignment) == Element::zero())
+    }
+}
+
+/// A reduced linear combination `scale * variable + constant`.
+struct Reduced<F: Field> {
+    scale: Element<F>,
+    variable: usize,
+    constant: Element<F>,
+}
+
+struct GateBuilder<F: Field> {
+    gates: Vec<PlonkGate<F>>,
+    variables: BTreeMap<Wire, usize>,
+    num_variables: usize,
+}
+
+impl<F: Field> GateBuilder<F> {
+    fn new_variable(&mut self) -> usize {
+        self.num_variables += 1;
+        self.num_variables - 1
+    }
+
+    fn gate(&mut self, a: usize, b: usize, c: usize, q: [Element<F>; 5]) {
+        let [q_m, q_l, q_r, q_o, q_c] = q;
+        self.gates.push(PlonkGate { a, b, c, q_m, q_l, q_r, q_o, q_c });
+    }
+
+    /// Reduces `exp` to a single variable, accumulating its terms into intermediate variables with
+    /// addition gates if it has more than one.
+    fn reduce(&mut self, exp: &Expression<F>) -> Reduced<F> {
+        let constant = exp.coefficients().get(&Wire::ONE).cloned().unwrap_or_else(Element::zero);
+        let terms: Vec<(usize, Element<F>)> = exp.coefficients().iter()
+            .filter(|(wire, coeff)| **wire != Wire::ONE && **coeff != Element::zero())
+            .map(|(wire, coeff)| (self.variables[wire], coeff.clone()))
+            .collect();
+        let (variable, scale) = match terms.split_first() {
+            None => (0, Element::zero()),
+            Some((first, [])) => first.clone(),
+            Some(((variable, scale), rest)) => {
+                let mut sum = (*variable, scale.clone());
+                for (variable, coeff) in rest {
+                    let out = self.new_variable();
+                    let (sum_variable, sum_scale) = sum;
+                    self.gate(sum_variable, *variable, out,
+                              [Element::zero(), sum_scale, coeff.clone(), -Element::one(), Element::zero()]);
+                    sum = (out, Element::one());
+                }
+                sum
+            }
+        };
+        Reduced { scale, variable, constant }
+    }
+
+    /// Adds a gate enforcing `a * b = c`.
+    fn multiply(&mut self, a: &Expression<F>, b: &Expression<F>, c: Reduced<F>) {
+        let a = self.reduce(a);
+        let b = self.reduce(b);
+        let q = [
+            a.scale.clone() * b.scale.clone(),
+            a.scale.clone() * b.constant.clone(),
+            a.constant.clone() * b.scale.clone(),
+            -c.scale,
+            a.constant * b.constant - c.constant,
+        ];
+        self.gate(a.variable, b.variable, c.variable, q);
+    }
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Converts the circuit's constraints into Plonk-style arithmetic gates. Wires are numbered in
+    /// the order `synthesize` allocates them, and each constraint becomes a multiplication gate,
+    /// preceded by addition gates for any linear combination of more than one wire. A conditional
+    /// constraint becomes a gate computing `a * b` into a new variable `t`, and gates enforcing
+    /// `selector * (t - c) = 0`, like its bellman encoding.
+    ///
+    /// Panics if the circuit has raw constraints, since they are bellman linear combinations.
+    pub fn to_plonk(&self) -> PlonkCircuit<F> {
+        assert!(self.raw_constraints().is_empty(), "raw constraints can't be converted to Plonk gates");
+        let wires = self.allocation_order();
+        let variables = wires.iter().enumerate()
+            .map(|(i, wire)| (*wire, i + 1))
+            .collect();
+        let mut builder = GateBuilder { gates: Vec::new(), variables, num_variables: wires.len() + 1 };
+        for constraint in &self.gadget().constraints {
+            let c = builder.reduce(&constraint.c);
+            builder.multiply(&constraint.a, &constraint.b, c);
+        }
+        for conditional in self.conditional_constraints() {
+            let constraint = &conditional.constraint;
+            let t = builder.new_variable();
+            let product = Reduced { scale: Element::one(), variable: t, constant: Element::zero() };
+            builder.multiply(&constraint.a, &constraint.b, product);
+            let c = builder.reduce(&constraint.c);
+            let difference = builder.new_variable();
+            builder.gate(t, c.variable, difference,
+                         [Element::zero(), Element::one(), -c.scale, -Element::one(), Element::zero()]);
+            let selector = builder.variables[&conditional.selector];
+            builder.gate(selector, difference, 0,
+                         [Element::one(), -c.constant, Element::zero(), Element::zero(), Element::zero()]);
+        }
+        let public_inputs = self.public_input_order().iter()
+            .map(|wire| builder.variables[wire])
+            .collect();
+        PlonkCircuit { gates: builder.gates, wires, num_variables: builder.num_variables, public_inputs }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use r1cs::{Element, Expression, GadgetBuilder, Wire, WireValues, Bn128 as Bn128F};
+
+    use crate::{PlonkGate, WrappedCircuit};
+    use crate::test_util::{conditional_circuit, product_circuit, scaled, ProductCircuit};
+
+    #[test]
+    fn product_constraint_as_gate() {
+        let plonk = product_circuit(BTreeMap::new()).to_plonk();
+        // Variables 1, 2 and 3 are x, y and z.
+        let expected = PlonkGate {
+            a: 1,
+            b: 2,
+            c: 3,
+            q_m: Element::one(),
+            q_l: Element::zero(),
+            q_r: Element::zero(),
+            q_o: -Element::<Bn128F>::one(),
+            q_c: Element::zero(),
+        };
+        assert_eq!(plonk.gates(), &[expected]);
+        assert_eq!(plonk.num_variables(), 4);
+        assert_eq!(plonk.public_inputs(), &[3]);
+    }
+
+    #[test]
+    fn linear_combinations_through_addition_gates() {
+        // (x + y + 1) * 2 = z.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let w = builder.wires(3);
+        let (x, y, z) = (w[0], w[1], w[2]);
+        let sum = Expression::from(&x) + Expression::from(&y) + Expression::from(&Wire::ONE);
+        builder.assert_product(&sum, &scaled(Wire::ONE, Element::from(2u8)), &Expression::from(&z));
+        let circuit: ProductCircuit = WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![z]);
+        let plonk = circuit.to_plonk();
+        assert_eq!(plonk.gates().len(), 2);
+        assert_eq!(plonk.num_variables(), 5);
+
+        for &(z_value, satisfied) in &[(12u8, true), (13, false)] {
+            let mut values = WireValues::<Bn128F>::new();
+            values.set(x, Element::from(2u8));
+            values.set(y, Element::from(3u8));
+            values.set(z, Element::from(z_value));
+            assert_eq!(plonk.is_satisfied(&plonk.assignment(&values)), satisfied);
+        }
+    }
+
+    #[test]
+    fn selector_gates_conditional_constraint() {
+        let plonk = conditional_circuit(BTreeMap::new()).to_plonk();
+        // x * y = z, then x * x = t, t - w = d and s * d = 0.
+        assert_eq!(plonk.gates().len(), 4);
+        assert_eq!(plonk.num_variables(), 8);
+
+        // x = 2, y = 3, z = 6, and w must be 4 only when s is nonzero.
+        for &(w, s, satisfied) in &[(4u8, 1u8, true), (5, 0, true), (5, 1, false)] {
+            let mut values = WireValues::<Bn128F>::new();
+            for (index, n) in (1..).zip(&[2u8, 3, 6, w, s]) {
+                values.set(Wire { index }, Element::from(*n));
+            }
+            assert_eq!(plonk.is_satisfied(&plonk.assignment(&values)), satisfied);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use bellman::{ConstraintSystem, Variable};
+use pairing::Engine;
+use r1cs::{Constraint, Element, Expression, Field, Gadget, Wire};
+
+use crate::{element_from_fr, Error, FieldConverter, WitnessSource, WrappedCircuit};
+
+/// A constraint `a * b = c` whose coefficients are already elements of the engine's field, such as
+/// one generated by a bellman-aware tool.
+#[derive(Clone, Debug)]
+pub struct FrConstraint<E: Engine> {
+    pub a: Vec<(Wire, E::Fr)>,
+    pub b: Vec<(Wire, E::Fr)>,
+    pub c: Vec<(Wire, E::Fr)>,
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Builds a circuit from constraints whose coefficients are already in `E::Fr`. `synthesize`
+    /// enforces them as given, without calling `convert_field`. The r1cs gadget, which methods like
+    /// `check_satisfied` and `fingerprint` work on, is derived from them once, here; this fails if
+    /// a coefficient doesn't fit in `F`.
+    pub fn from_fr_constraints(
+        constraints: Vec<FrConstraint<E>>,
+        witness_map: BTreeMap<u32, E::Fr>,
+        public_inputs: Vec<Wire>
+    ) -> Result<Self, Error> {
+        let expression = |terms: &[(Wire, E::Fr)]| -> Result<Expression<F>, Error> {
+            let mut coefficients: BTreeMap<Wire, Element<F>> = BTreeMap::new();
+            for (wire, fr) in terms {
+                let coeff = element_from_fr(fr)?;
+                let sum = coefficients.remove(wire).unwrap_or_else(Element::zero) + coeff;
+                coefficients.insert(*wire, sum);
+            }
+            Ok(Expression::new(coefficients))
+        };
+        let gadget_constraints = constraints.iter()
+            .map(|constraint| Ok(Constraint {
+                a: expression(&constraint.a)?,
+                b: expression(&constraint.b)?,
+                c: expression(&constraint.c)?,
+            }))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let gadget = Gadget { constraints: gadget_constraints, witness_generators: Vec::new() };
+        let mut circuit = Self::new(gadget, witness_map, public_inputs);
+        circuit.preconverted = Some(Arc::new(constraints));
+        Ok(circuit)
+    }
+
+    /// Converts the gadget's coefficients once, so that this circuit and every copy made by
+    /// `with_witness_map`, such as those a `Groth16Prover` proves with, skip `convert_field` when
+    /// synthesized. Methods which change the constraints, like `merge_equal_wires`, drop the
+    /// converted coefficients.
+    pub fn with_preconverted_coefficients(mut self) -> Self {
+        let convert = |exp: &Expression<F>| exp.coefficients().iter()
+            .map(|(wire, coeff)| (*wire, C::convert_field(coeff)))
+            .collect();
+        let constraints = self.gadget().constraints.iter()
+            .map(|constraint| FrConstraint {
+                a: convert(&constraint.a),
+                b: convert(&constraint.b),
+                c: convert(&constraint.c),
+            })
+            .collect();
+        self.preconverted = Some(Arc::new(constraints));
+        self
+    }
+
+    /// Returns whether `synthesize` will use preconverted coefficients.
+    pub fn is_preconverted(&self) -> bool {
+        self.preconverted.is_some()
+    }
+
+    /// Like `_synthesize_constraints`, but for preconverted constraints.
+    pub(crate) fn _synthesize_preconverted<CS: ConstraintSystem<E>, W: WitnessSource<E::Fr> + ?Sized>(
+        cs: &mut CS,
+        constraints: &[FrConstraint<E>],
+        witness: &W,
+        public_inputs: &HashSet<Wire>,
+        preallocated: &[Wire]
+    ) -> BTreeMap<Wire, Variable> {
+        let mut variable_map = Self::_preallocate(cs, witness, public_inputs, preallocated);
+        for (i, constraint) in constraints.iter().enumerate() {
+            let mut lc = |terms: &[(Wire, E::Fr)]| Self::_build_lc(
+                cs, terms.iter().cloned(), &mut variable_map, witness, public_inputs);
+            let (a_lc, b_lc, c_lc) = (lc(&constraint.a), lc(&constraint.b), lc(&constraint.c));
+            cs.enforce(
+                || format!("generated by r1cs-bellman at {}", i),
+                |_| a_lc,
+                |_| b_lc,
+                |_| c_lc,
+            );
+        }
+        variable_map
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use bellman::Circuit;
+    use pairing::compact_bn256::Bn256;
+    use r1cs::{Element, Expression, GadgetBuilder, Wire, Bn128 as Bn128F};
+    use rand::thread_rng;
+
+    use crate::{CollectingConstraintSystem, FrConstraint, Groth16Prover, WrappedCircuit};
+    use crate::test_util::{fr, scaled, ProductCircuit};
+
+    #[test]
+    fn preconverted_matches_converted() {
+        // 3 x * y = z + 5, with z public.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let w = builder.wires(3);
+        let (x, y, z) = (w[0], w[1], w[2]);
+        let c = scaled(Wire::ONE, Element::from(5u8)) + Expression::from(&z);
+        builder.assert_product(&scaled(x, Element::from(3u8)), &Expression::from(&y), &c);
+        let mut witness_map = BTreeMap::new();
+        for (wire, n) in &[(x, 2u8), (y, 3), (z, 13)] {
+            witness_map.insert(wire.index, fr(*n));
+        }
+        let converted: ProductCircuit = WrappedCircuit::new(builder.build(), witness_map.clone(), vec![z]);
+
+        let constraint = FrConstraint {
+            a: vec![(x, fr(3))],
+            b: vec![(y, fr(1))],
+            c: vec![(Wire::ONE, fr(5)), (z, fr(1))],
+        };
+        let preconverted: ProductCircuit =
+            WrappedCircuit::from_fr_constraints(vec![constraint], witness_map, vec![z]).unwrap();
+        assert!(preconverted.is_preconverted());
+        assert_eq!(preconverted.fingerprint(), converted.fingerprint());
+        assert_eq!(preconverted.check_satisfied(), Ok(()));
+
+        let matrices = |circuit: &ProductCircuit| {
+            let mut cs = CollectingConstraintSystem::<Bn256>::new();
+            circuit.with_witness_map(circuit.witness_map().clone()).synthesize(&mut cs).unwrap();
+            cs.matrices()
+        };
+        assert_eq!(matrices(&preconverted), matrices(&converted));
+        let cached = converted.with_witness_map(converted.witness_map().clone())
+            .with_preconverted_coefficients();
+        assert_eq!(matrices(&cached), matrices(&converted));
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&converted, rng).unwrap();
+        let proof = prover.prove(preconverted.witness_map().clone(), rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(13)]).unwrap());
+        let prover = Groth16Prover::setup(&preconverted, rng).unwrap();
+        let proof = prover.prove(preconverted.witness_map().clone(), rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(13)]).unwrap());
+    }
+}
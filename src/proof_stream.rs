@@ -0,0 +1,149 @@
+//! Verification of a stream of proofs, such as a verification daemon reads from a socket.
+//!
+//! The stream is a sequence of frames, each a 4-byte little-endian length followed by that many
+//! bytes: a proof as written by `Proof::write`, then its public inputs in the canonical layout of
+//! `write_public_inputs`.
+
+use std::io::{self, Read, Write};
+use bellman::groth16::Proof;
+use pairing::Engine;
+
+use crate::{read_public_inputs, write_public_inputs, CachedVerifier, Error};
+
+/// Writes one frame of a proof stream.
+pub fn write_proof_frame<E: Engine, W: Write>(
+    proof: &Proof<E>,
+    public_inputs: &[E::Fr],
+    mut writer: W
+) -> io::Result<()> {
+    let mut payload = Vec::new();
+    proof.write(&mut payload)?;
+    write_public_inputs(public_inputs, &mut payload)?;
+    writer.write_all(&(payload.len() as u32).to_le_bytes())?;
+    writer.write_all(&payload)
+}
+
+/// Reads frames from a reader and verifies each against one prepared verifying key, yielding one
+/// result per frame.
+///
+/// A malformed frame yields an error, and verification continues with the next frame, since its
+/// length is known. If the reader fails, or ends partway through a frame, an error is yielded and
+/// the iterator ends. If it ends between frames, the iterator ends without one.
+pub struct ProofStreamVerifier<E: Engine, R: Read> {
+    verifier: CachedVerifier<E>,
+    reader: R,
+    finished: bool,
+}
+
+impl<E: Engine, R: Read> ProofStreamVerifier<E, R> {
+    pub fn new(verifier: CachedVerifier<E>, reader: R) -> Self {
+        Self { verifier, reader, finished: false }
+    }
+
+    /// Reads the length of the next frame, or `None` if the stream ended before it.
+    fn read_length(&mut self) -> io::Result<Option<u32>> {
+        let mut bytes = [0u8; 4];
+        let mut read = 0;
+        while read < bytes.len() {
+            match self.reader.read(&mut bytes[read..]) {
+                Ok(0) if read == 0 => return Ok(None),
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated frame length")),
+                Ok(n) => read += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(Some(u32::from_le_bytes(bytes)))
+    }
+
+    /// Reads and verifies a frame of `length` bytes. The outer error is a failure of the stream
+    /// itself, and the inner one a malformed frame.
+    fn verify_frame(&mut self, length: u32) -> io::Result<Result<bool, Error>> {
+        let mut frame = (&mut self.reader).take(u64::from(length));
+        let parsed = Proof::<E>::read(&mut frame)
+            .and_then(|proof| Ok((proof, read_public_inputs(&mut frame)?)));
+        let trailing = io::copy(&mut frame, &mut io::sink())?;
+        if frame.limit() > 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated frame"));
+        }
+        let (proof, public_inputs) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => return Ok(Err(Error::Io(e))),
+        };
+        if trailing > 0 {
+            return Ok(Err(Error::InvalidFormat("trailing bytes in frame".to_string())));
+        }
+        Ok(self.verifier.verify(&proof, &public_inputs).map_err(Error::Synthesis))
+    }
+}
+
+impl<E: Engine, R: Read> Iterator for ProofStreamVerifier<E, R> {
+    type Item = Result<bool, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let result = match self.read_length() {
+            Ok(None) => {
+                self.finished = true;
+                return None;
+            }
+            Ok(Some(length)) => self.verify_frame(length),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(result) => Some(result),
+            Err(e) => {
+                self.finished = true;
+                Some(Err(Error::Io(e)))
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::io::Cursor;
+    use rand::thread_rng;
+
+    use crate::{write_proof_frame, Error, Groth16Prover, ProofStreamVerifier};
+    use crate::test_util::{fr, product_circuit, product_witness};
+
+    #[test]
+    fn verify_concatenated_frames() {
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&product_circuit(BTreeMap::new()), rng).unwrap();
+        let proof = prover.prove(product_witness(2, 3, 6), rng).unwrap();
+
+        let mut stream = Vec::new();
+        write_proof_frame(&proof, &[fr(6)], &mut stream).unwrap();
+        write_proof_frame(&proof, &[fr(7)], &mut stream).unwrap();
+        // A frame too short for a proof.
+        stream.extend_from_slice(&3u32.to_le_bytes());
+        stream.extend_from_slice(&[1, 2, 3]);
+        write_proof_frame(&proof, &[fr(6)], &mut stream).unwrap();
+        let complete = stream.len();
+
+        let verifier = || prover.verifying_key_bundle().verifier();
+        let results: Vec<_> = ProofStreamVerifier::new(verifier(), Cursor::new(&stream)).collect();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0].as_ref().ok(), Some(&true));
+        assert_eq!(results[1].as_ref().ok(), Some(&false));
+        assert!(matches!(results[2], Err(Error::Io(_))));
+        assert_eq!(results[3].as_ref().ok(), Some(&true));
+
+        // The connection drops partway through a final frame.
+        write_proof_frame(&proof, &[fr(6)], &mut stream).unwrap();
+        stream.truncate(complete + 10);
+        let mut results = ProofStreamVerifier::new(verifier(), Cursor::new(&stream));
+        assert_eq!(results.by_ref().filter(|result| result.is_ok()).count(), 3);
+        assert!(results.next().is_none());
+        let last = ProofStreamVerifier::new(verifier(), Cursor::new(&stream)).last().unwrap();
+        match last {
+            Err(Error::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof),
+            _ => panic!("expected a truncated frame"),
+        }
+    }
+}
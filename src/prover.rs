@@ -0,0 +1,418 @@
+use std::collections::BTreeMap;
+use bellman::{Circuit, SynthesisError};
+use bellman::groth16::{
+    create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof,
+    Parameters, PreparedVerifyingKey, Proof};
+use pairing::Engine;
+use r1cs::{Field, Gadget, Wire};
+use rand::{ChaChaRng, Rng, SeedableRng};
+
+use crate::{CollectingConstraintSystem, Error, FieldConverter, Fingerprint, VerificationBundle};
+use crate::{VerifyingKeyBundle, WrappedCircuit};
+
+/// Runs the Groth16 setup for a circuit once, then proves statements about it with any number of
+/// witnesses.
+pub struct Groth16Prover<F: Field, E: Engine, C: FieldConverter<F, E>> {
+    circuit: WrappedCircuit<F, E, C>,
+    params: Parameters<E>,
+    pvk: PreparedVerifyingKey<E>,
+    fingerprint: Fingerprint,
+    num_public_inputs: usize,
+    strict_witness: bool,
+    self_verify: bool,
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> Groth16Prover<F, E, C> {
+    /// Generates random parameters for the given circuit. Its witness, if any, is ignored.
+    pub fn setup<R: Rng>(
+        circuit: &WrappedCircuit<F, E, C>,
+        rng: &mut R
+    ) -> Result<Self, SynthesisError> {
+        let circuit = circuit.with_witness_map(BTreeMap::new());
+        let params = generate_random_parameters(&circuit, rng)?;
+        Ok(Self::new(circuit, params))
+    }
+
+    /// Wraps parameters generated elsewhere, such as by a trusted setup ceremony, for proving the
+    /// given circuit. Its witness, if any, is ignored. Parameters don't record which circuit they
+    /// were generated for, so if the supplier published its fingerprint, pass it as `fingerprint`
+    /// to have it checked. Either way, this checks that the parameters have the shape the circuit
+    /// needs, failing with `ParametersMismatch` if they have the wrong number of input or
+    /// auxiliary variables.
+    pub fn from_parameters(
+        circuit: &WrappedCircuit<F, E, C>,
+        params: Parameters<E>,
+        fingerprint: Option<Fingerprint>
+    ) -> Result<Self, Error> {
+        let circuit = circuit.with_witness_map(BTreeMap::new());
+        if let Some(expected) = fingerprint {
+            let actual = circuit.fingerprint();
+            if actual != expected {
+                return Err(Error::FingerprintMismatch { expected, actual });
+            }
+        }
+        let mut cs = CollectingConstraintSystem::<E>::new();
+        (&circuit).synthesize(&mut cs)?;
+        if params.vk.ic.len() != cs.inputs.len() || params.l.len() != cs.aux.len() {
+            return Err(Error::ParametersMismatch);
+        }
+        Ok(Self::new(circuit, params))
+    }
+
+    fn new(circuit: WrappedCircuit<F, E, C>, params: Parameters<E>) -> Self {
+        let pvk = prepare_verifying_key(&params.vk);
+        let fingerprint = circuit.fingerprint();
+        let num_public_inputs = circuit.public_input_order().len();
+        Self {
+            circuit,
+            params,
+            pvk,
+            fingerprint,
+            num_public_inputs,
+            strict_witness: false,
+            self_verify: false,
+        }
+    }
+
+    /// In strict witness mode, proving fails unless every wire referenced by the constraints has a
+    /// witness, rather than treating missing values as zero.
+    pub fn with_strict_witness(mut self, strict_witness: bool) -> Self {
+        self.strict_witness = strict_witness;
+        self
+    }
+
+    pub fn parameters(&self) -> &Parameters<E> {
+        &self.params
+    }
+
+    pub fn prepared_verifying_key(&self) -> &PreparedVerifyingKey<E> {
+        &self.pvk
+    }
+
+    /// Returns the verifying key with the circuit's fingerprint and public input count, for
+    /// distributing to verifiers without the proving parameters.
+    pub fn verifying_key_bundle(&self) -> VerifyingKeyBundle<E> {
+        VerifyingKeyBundle {
+            fingerprint: self.fingerprint,
+            num_public_inputs: self.num_public_inputs,
+            vk: self.params.vk.clone(),
+        }
+    }
+
+    /// The fingerprint of the circuit the parameters were generated for.
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.fingerprint
+    }
+
+    /// The number of public inputs the circuit exposes, and so the number `verify` expects.
+    pub fn num_public_inputs(&self) -> usize {
+        self.num_public_inputs
+    }
+
+    /// Returns the circuit the parameters were generated for, with the given witness.
+    pub fn circuit(&self, witness_map: BTreeMap<u32,E::Fr>) -> WrappedCircuit<F, E, C> {
+        self.circuit.with_witness_map(witness_map)
+    }
+
+    pub fn prove<R: Rng>(
+        &self,
+        witness_map: BTreeMap<u32,E::Fr>,
+        rng: &mut R
+    ) -> Result<Proof<E>, Error> {
+        self.create_proof(self.circuit(witness_map), rng)
+    }
+
+    /// Like `prove`, but also verifies the proof against the public input values derived from the
+    /// witness before returning it, failing with `SelfVerificationFailed` if it doesn't verify.
+    /// That indicates a bug, such as a witness which doesn't satisfy the constraints, or a broken
+    /// field conversion. The check costs a verification, so it only runs in debug builds, unless
+    /// enabled with `with_self_verification`.
+    pub fn prove_checked<R: Rng>(
+        &self,
+        witness_map: BTreeMap<u32,E::Fr>,
+        rng: &mut R
+    ) -> Result<Proof<E>, Error> {
+        let circuit = self.circuit(witness_map);
+        let public_inputs = circuit.public_input_values();
+        let proof = self.create_proof(circuit, rng)?;
+        if (cfg!(debug_assertions) || self.self_verify) && !self.verify(&proof, &public_inputs)? {
+            return Err(Error::SelfVerificationFailed);
+        }
+        Ok(proof)
+    }
+
+    /// Makes `prove_checked` verify its proofs in release builds as well as debug builds.
+    pub fn with_self_verification(mut self, self_verify: bool) -> Self {
+        self.self_verify = self_verify;
+        self
+    }
+
+    /// Proves a circuit built by the caller, such as one generated afresh for each witness. Since
+    /// the parameters are only valid for the circuit they were generated for, this fails if the
+    /// circuit's fingerprint differs from the one recorded at setup, which happens when a gadget's
+    /// constraints depend on its witness.
+    pub fn prove_circuit<R: Rng>(
+        &self,
+        circuit: WrappedCircuit<F, E, C>,
+        rng: &mut R
+    ) -> Result<Proof<E>, Error> {
+        let actual = circuit.fingerprint();
+        if actual != self.fingerprint {
+            return Err(Error::FingerprintMismatch { expected: self.fingerprint, actual });
+        }
+        self.create_proof(circuit, rng)
+    }
+
+    /// Proves the statement given by `witness_map`, and bundles the proof with everything needed to
+    /// verify it.
+    pub fn prove_bundle<R: Rng>(
+        &self,
+        witness_map: BTreeMap<u32,E::Fr>,
+        rng: &mut R
+    ) -> Result<VerificationBundle<E>, Error> {
+        let circuit = self.circuit(witness_map);
+        let public_inputs = circuit.public_input_values();
+        let proof = self.create_proof(circuit, rng)?;
+        Ok(VerificationBundle {
+            fingerprint: self.fingerprint,
+            metadata: self.circuit.metadata().clone(),
+            vk: self.params.vk.clone(),
+            public_inputs,
+            proof,
+        })
+    }
+
+    /// Verifies a proof, first checking that the number of public inputs is the number recorded at
+    /// setup, and failing with `PublicInputCountMismatch` if not.
+    pub fn verify(&self, proof: &Proof<E>, public_inputs: &[E::Fr]) -> Result<bool, Error> {
+        if public_inputs.len() != self.num_public_inputs {
+            return Err(Error::PublicInputCountMismatch {
+                expected: self.num_public_inputs,
+                actual: public_inputs.len(),
+            });
+        }
+        Ok(verify_proof(&self.pvk, proof, public_inputs)?)
+    }
+
+    fn create_proof<R: Rng>(
+        &self,
+        circuit: WrappedCircuit<F, E, C>,
+        rng: &mut R
+    ) -> Result<Proof<E>, Error> {
+        if self.strict_witness {
+            circuit.require_full_witness(true).map_err(Error::MissingWitness)?;
+        }
+        Ok(create_random_proof(circuit, &self.params, rng)?)
+    }
+}
+
+/// Sets up, proves and verifies a gadget in one call, returning whether the proof verifies against
+/// the public input values in `witness_map`. The proof is serialized and read back before being
+/// verified, as it would be when sent to a verifier. Since this runs a fresh setup each time, it is
+/// meant for unit testing gadgets; `Groth16Prover` reuses its parameters.
+pub fn prove_and_verify<F, E, C, R>(
+    gadget: Gadget<F>,
+    witness_map: BTreeMap<u32, E::Fr>,
+    public_inputs: Vec<Wire>,
+    rng: &mut R
+) -> Result<bool, Error>
+where
+    F: Field,
+    E: Engine,
+    C: FieldConverter<F, E>,
+    R: Rng,
+{
+    let circuit = WrappedCircuit::<F, E, C>::new(gadget, witness_map, public_inputs);
+    let prover = Groth16Prover::setup(&circuit, rng)?;
+    let proof = prover.prove(circuit.witness_map().clone(), rng)?;
+    let mut proof_bytes = vec![];
+    proof.write(&mut proof_bytes)?;
+    let proof = Proof::read(&proof_bytes[..])?;
+    prover.verify(&proof, &circuit.public_input_values())
+}
+
+/// Generates parameters for `circuit` deterministically from `seed`, so that tests and CI get the
+/// same parameters on every run.
+///
+/// These are for testing only: anyone who knows the seed can recompute the toxic waste and forge
+/// proofs. Production parameters must come from `generate_random_parameters` with a secure RNG, or
+/// from a trusted setup ceremony.
+pub fn generate_test_parameters<F: Field, E: Engine, C: FieldConverter<F, E>>(
+    circuit: &WrappedCircuit<F, E, C>,
+    seed: [u8; 32]
+) -> Result<Parameters<E>, SynthesisError> {
+    let mut words = [0u32; 8];
+    for (word, bytes) in words.iter_mut().zip(seed.chunks(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    let mut rng = ChaChaRng::from_seed(&words[..]);
+    generate_random_parameters(circuit.with_witness_map(BTreeMap::new()), &mut rng)
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use bellman::groth16::Parameters;
+    use pairing::compact_bn256::{Bn256, Fr};
+    use rand::thread_rng;
+
+    use r1cs::{Expression, GadgetBuilder, Wire, Bn128 as Bn128F};
+
+    use crate::{generate_test_parameters, prove_and_verify, Bn256Converter, Error, Groth16Prover};
+    use crate::{VerificationBundle, WrappedCircuit};
+    use crate::test_util::{fr, product_circuit, product_witness, ProductCircuit};
+
+    #[test]
+    fn prove_and_verify() {
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&product_circuit(BTreeMap::new()), rng).unwrap();
+        let proof = prover.prove(product_witness(2, 3, 6), rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(6)]).unwrap());
+        assert!(!prover.verify(&proof, &[fr(7)]).unwrap());
+    }
+
+    #[test]
+    fn prove_and_verify_in_one_call() {
+        let rng = &mut thread_rng();
+        for (witness_map, expected) in vec![(product_witness(2, 3, 6), true), (product_witness(3, 6, 32), false)] {
+            let mut builder = GadgetBuilder::<Bn128F>::new();
+            let x = builder.wire();
+            let y = builder.wire();
+            let z = builder.wire();
+            builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+            let verified = prove_and_verify::<_, Bn256, Bn256Converter, _>(
+                builder.build(), witness_map, vec![z], rng).unwrap();
+            assert_eq!(verified, expected);
+        }
+    }
+
+    #[test]
+    fn prove_with_reloaded_parameters() {
+        let rng = &mut thread_rng();
+        let circuit = product_circuit(BTreeMap::new());
+        let mut params_bytes = vec![];
+        generate_test_parameters(&circuit, [3; 32]).unwrap().write(&mut params_bytes).unwrap();
+
+        let params = Parameters::<Bn256>::read(&params_bytes[..], true).unwrap();
+        let prover = Groth16Prover::from_parameters(&circuit, params, Some(circuit.fingerprint())).unwrap();
+        let proof = prover.prove(product_witness(2, 3, 6), rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(6)]).unwrap());
+
+        let (other, _) = product_circuit(BTreeMap::new()).with_context_input();
+        let params = Parameters::<Bn256>::read(&params_bytes[..], true).unwrap();
+        let result = Groth16Prover::from_parameters(&other, params, None);
+        assert!(matches!(result, Err(Error::ParametersMismatch)));
+        let params = Parameters::<Bn256>::read(&params_bytes[..], true).unwrap();
+        let result = Groth16Prover::from_parameters(&other, params, Some(circuit.fingerprint()));
+        assert!(matches!(result, Err(Error::FingerprintMismatch { .. })));
+    }
+
+    #[test]
+    fn verify_checks_public_input_count() {
+        let rng = &mut thread_rng();
+        let (circuit, _) = product_circuit(BTreeMap::new()).with_context_input();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        assert_eq!(prover.num_public_inputs(), 2);
+        let mut witness_map = product_witness(2, 3, 6);
+        witness_map.insert(4, fr(1));
+        let proof = prover.prove(witness_map, rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(6), fr(1)]).unwrap());
+        match prover.verify(&proof, &[fr(6)]) {
+            Err(Error::PublicInputCountMismatch { expected, actual }) => {
+                assert_eq!(expected, 2);
+                assert_eq!(actual, 1);
+            }
+            _ => panic!("expected a public input count mismatch"),
+        }
+    }
+
+    #[test]
+    fn test_parameters_are_deterministic() {
+        let circuit = product_circuit(BTreeMap::new());
+        let serialize = |seed| {
+            let mut bytes = vec![];
+            generate_test_parameters(&circuit, seed).unwrap().write(&mut bytes).unwrap();
+            bytes
+        };
+        assert_eq!(serialize([7; 32]), serialize([7; 32]));
+        assert_ne!(serialize([7; 32]), serialize([8; 32]));
+    }
+
+    #[test]
+    fn bundle_round_trip() {
+        let rng = &mut thread_rng();
+        let circuit = product_circuit(BTreeMap::new()).with_metadata("name", "product");
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        let bundle = prover.prove_bundle(product_witness(2, 3, 6), rng).unwrap();
+
+        let mut bundle_out = vec![];
+        bundle.write(&mut bundle_out).unwrap();
+        let bundle = VerificationBundle::<Bn256>::read(&bundle_out[..]).unwrap();
+
+        assert_eq!(bundle.fingerprint, circuit.fingerprint());
+        assert_eq!(&bundle.metadata, circuit.metadata());
+        assert_eq!(bundle.public_inputs, vec![fr(6)]);
+        assert!(bundle.verify(&prover.parameters().vk).unwrap());
+
+        // A bundle carrying the key of another setup is rejected.
+        let other = Groth16Prover::setup(&circuit, rng).unwrap();
+        assert!(matches!(bundle.verify(&other.parameters().vk), Err(Error::VerifyingKeyMismatch)));
+    }
+
+    /// Builds a product circuit which, when a witness is present, also asserts that `z` is boolean.
+    fn witness_dependent_circuit(witness_map: BTreeMap<u32, Fr>) -> ProductCircuit {
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let z = builder.wire();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+        if !witness_map.is_empty() {
+            builder.assert_product(&Expression::from(&z), &Expression::from(&z), &Expression::from(&z));
+        }
+        WrappedCircuit::new(builder.build(), witness_map, vec![z])
+    }
+
+    #[test]
+    fn prove_circuit_rejects_shape_mismatch() {
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&witness_dependent_circuit(BTreeMap::new()), rng).unwrap();
+
+        assert!(prover.prove_circuit(product_circuit(product_witness(2, 3, 6)), rng).is_ok());
+        match prover.prove_circuit(witness_dependent_circuit(product_witness(1, 1, 1)), rng) {
+            Err(Error::FingerprintMismatch { expected, actual }) => {
+                assert_eq!(expected, prover.fingerprint());
+                assert_ne!(expected, actual);
+            }
+            _ => panic!("expected a fingerprint mismatch"),
+        }
+    }
+
+    #[test]
+    fn strict_mode_rejects_missing_witness() {
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&product_circuit(BTreeMap::new()), rng).unwrap()
+            .with_strict_witness(true);
+        let mut witness_map = product_witness(2, 3, 6);
+        witness_map.remove(&2);
+        match prover.prove(witness_map, rng) {
+            Err(Error::MissingWitness(wires)) => assert_eq!(wires, vec![Wire { index: 2 }]),
+            _ => panic!("expected a missing witness error"),
+        }
+        assert!(prover.prove(product_witness(2, 3, 6), rng).is_ok());
+    }
+
+    #[test]
+    fn prove_checked_catches_bad_witness() {
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&product_circuit(BTreeMap::new()), rng).unwrap()
+            .with_self_verification(true);
+
+        // bellman happily proves an unsatisfied circuit; the proof just doesn't verify.
+        assert!(prover.prove(product_witness(2, 3, 7), rng).is_ok());
+        match prover.prove_checked(product_witness(2, 3, 7), rng) {
+            Err(Error::SelfVerificationFailed) => {}
+            _ => panic!("expected a self-verification failure"),
+        }
+        assert!(prover.prove_checked(product_witness(2, 3, 6), rng).is_ok());
+    }
+}
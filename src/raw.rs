@@ -0,0 +1,97 @@
+use bellman::{Index, LinearCombination, Variable};
+use pairing::Engine;
+use pairing::ff::{PrimeField, PrimeFieldRepr};
+use r1cs::{Field, Wire};
+use sha2::{Digest, Sha256};
+
+use crate::{FieldConverter, WrappedCircuit};
+
+/// A constraint `a * b = c` given directly as bellman linear combinations, such as one produced by
+/// another gadget library. Raw constraints bypass the r1cs wire model: they are enforced verbatim,
+/// after the converted r1cs constraints, and are not covered by checks like `check_satisfied`.
+///
+/// Their linear combinations may only reference `one_variable()` and the variables of the
+/// circuit's wires, as given by `WrappedCircuit::variable_of`.
+#[derive(Clone)]
+pub struct RawConstraint<E: Engine> {
+    pub a: LinearCombination<E>,
+    pub b: LinearCombination<E>,
+    pub c: LinearCombination<E>,
+}
+
+impl<E: Engine> RawConstraint<E> {
+    pub(crate) fn hash_into(&self, hasher: &mut Sha256) {
+        for lc in &[&self.a, &self.b, &self.c] {
+            hasher.update(&(lc.as_ref().len() as u32).to_le_bytes());
+            for (variable, coeff) in lc.as_ref() {
+                let (kind, index) = match variable.get_unchecked() {
+                    Index::Input(i) => (0u8, i),
+                    Index::Aux(i) => (1u8, i),
+                };
+                let mut coeff_bytes = Vec::new();
+                coeff.into_repr().write_le(&mut coeff_bytes).unwrap();
+                hasher.update(&[kind]);
+                hasher.update(&(index as u64).to_le_bytes());
+                hasher.update(&coeff_bytes);
+            }
+        }
+    }
+}
+
+/// The variable bellman reserves for the constant one.
+pub fn one_variable() -> Variable {
+    Variable::new_unchecked(Index::Input(0))
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Appends a raw constraint, to be enforced after the converted r1cs constraints.
+    pub fn add_raw_constraint(&mut self, constraint: RawConstraint<E>) {
+        self.raw_constraints.push(constraint);
+    }
+
+    pub fn raw_constraints(&self) -> &[RawConstraint<E>] {
+        &self.raw_constraints
+    }
+
+    /// Returns the bellman variable `synthesize` will allocate for `wire`, or `None` if no
+    /// constraint references it.
+    pub fn variable_of(&self, wire: Wire) -> Option<Variable> {
+        self.variable_indices().into_iter()
+            .find(|(w, _)| *w == wire)
+            .map(|(_, index)| Variable::new_unchecked(index))
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use bellman::LinearCombination;
+    use r1cs::Wire;
+    use rand::thread_rng;
+
+    use crate::{one_variable, Groth16Prover, RawConstraint};
+    use crate::test_util::{fr, product_circuit, product_witness};
+
+    #[test]
+    fn raw_and_converted_constraints_in_one_proof() {
+        // Alongside the converted x * y = z, enforce the raw constraint z * 1 = 6.
+        let mut circuit = product_circuit(BTreeMap::new());
+        let z = circuit.variable_of(Wire { index: 3 }).unwrap();
+        circuit.add_raw_constraint(RawConstraint {
+            a: LinearCombination::zero() + z,
+            b: LinearCombination::zero() + one_variable(),
+            c: LinearCombination::zero() + (fr(6), one_variable()),
+        });
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        assert_ne!(prover.fingerprint(), product_circuit(BTreeMap::new()).fingerprint());
+
+        let proof = prover.prove(product_witness(2, 3, 6), rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(6)]).unwrap());
+
+        // 2 * 4 = 8 satisfies the converted constraint, but not the raw one.
+        let proof = prover.prove(product_witness(2, 4, 8), rng).unwrap();
+        assert!(!prover.verify(&proof, &[fr(8)]).unwrap());
+    }
+}
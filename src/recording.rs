@@ -0,0 +1,159 @@
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use num::{BigUint, One};
+use pairing::Engine;
+use r1cs::{Element, Field};
+
+use crate::FieldConverter;
+
+thread_local! {
+    static MAX_OBSERVED: RefCell<Option<BigUint>> = RefCell::new(None);
+    static POSSIBLE_NEGATIVES: RefCell<Vec<BigUint>> = RefCell::new(Vec::new());
+}
+
+/// A converter which delegates to `C`, while recording the largest element it has converted on the
+/// current thread, including coefficients converted during synthesis. A maximum close to the
+/// modulus often means a witness holds an unintended negative value.
+///
+/// Since converters are stateless, the maximum is kept in a thread local, shared by every
+/// `RecordingConverter` regardless of `C`.
+pub struct RecordingConverter<C> {
+    _c: PhantomData<C>,
+}
+
+impl<C> RecordingConverter<C> {
+    /// Returns the largest element converted on this thread since the last `reset`, if any.
+    pub fn max_observed() -> Option<BigUint> {
+        MAX_OBSERVED.with(|max| max.borrow().clone())
+    }
+
+    /// Forgets the elements converted so far on this thread.
+    pub fn reset() {
+        MAX_OBSERVED.with(|max| *max.borrow_mut() = None);
+    }
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> FieldConverter<F, E> for RecordingConverter<C> {
+    fn convert_field(n: &Element<F>) -> E::Fr {
+        MAX_OBSERVED.with(|max| {
+            let mut max = max.borrow_mut();
+            if max.as_ref().map_or(true, |m| n.to_biguint() > m) {
+                *max = Some(n.to_biguint().clone());
+            }
+        });
+        C::convert_field(n)
+    }
+
+    fn is_compatible() -> bool {
+        C::is_compatible()
+    }
+}
+
+/// Returns whether `n` lies in `(modulus - 2^32, modulus)`, as a small negative integer reduced
+/// modulo `modulus` would. Such values are often produced by accident, such as by subtracting
+/// integers before converting them, though they are sometimes intended.
+pub fn looks_negative(n: &BigUint, modulus: &BigUint) -> bool {
+    n < modulus && modulus - n < BigUint::one() << 32
+}
+
+/// A converter which delegates to `C`, while recording every element it converts on the current
+/// thread which `looks_negative` in `F`. This is advisory: conversion proceeds as normal.
+///
+/// As with `RecordingConverter`, the record is a thread local, shared regardless of `C`.
+pub struct LintingConverter<C> {
+    _c: PhantomData<C>,
+}
+
+impl<C> LintingConverter<C> {
+    /// Returns the elements flagged on this thread since the last `reset`, in conversion order.
+    pub fn possible_negatives() -> Vec<BigUint> {
+        POSSIBLE_NEGATIVES.with(|flagged| flagged.borrow().clone())
+    }
+
+    pub fn reset() {
+        POSSIBLE_NEGATIVES.with(|flagged| flagged.borrow_mut().clear());
+    }
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> FieldConverter<F, E> for LintingConverter<C> {
+    fn convert_field(n: &Element<F>) -> E::Fr {
+        if looks_negative(n.to_biguint(), &F::order()) {
+            POSSIBLE_NEGATIVES.with(|flagged| flagged.borrow_mut().push(n.to_biguint().clone()));
+        }
+        C::convert_field(n)
+    }
+
+    fn is_compatible() -> bool {
+        C::is_compatible()
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use num::{BigUint, One};
+    use pairing::compact_bn256::Bn256;
+    use r1cs::{Element, Expression, Field, GadgetBuilder, WireValues, Bn128 as Bn128F};
+    use rand::thread_rng;
+
+    use crate::{Bn256Converter, Groth16Prover, LintingConverter, RecordingConverter, WrappedCircuit};
+    use crate::test_util::product_circuit;
+
+    type Recording = RecordingConverter<Bn256Converter>;
+
+    #[test]
+    fn records_near_modulus_witness() {
+        Recording::reset();
+        assert_eq!(Recording::max_observed(), None);
+
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let z = builder.wire();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+        let circuit: WrappedCircuit<Bn128F, Bn256, Recording> =
+            WrappedCircuit::new(builder.build(), Default::default(), vec![z]);
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        assert_eq!(Recording::max_observed(), Some(BigUint::one()));
+
+        // -1 * 1 = -1.
+        let mut values = WireValues::new();
+        values.set(x, Element::largest_element());
+        values.set(y, Element::one());
+        values.set(z, Element::largest_element());
+        let witness_map = circuit.witness_from_wire_values(&values);
+        let public_inputs = prover.circuit(witness_map.clone()).public_input_values();
+        let proof = prover.prove(witness_map, rng).unwrap();
+        assert!(prover.verify(&proof, &public_inputs).unwrap());
+        assert_eq!(Recording::max_observed(), Some(Bn128F::order() - BigUint::one()));
+
+        // Converters other than RecordingConverter don't record anything.
+        Recording::reset();
+        product_circuit(Default::default()).witness_from_wire_values(&values);
+        assert_eq!(Recording::max_observed(), None);
+    }
+
+    #[test]
+    fn flags_near_modulus_values() {
+        type Linting = LintingConverter<Bn256Converter>;
+        Linting::reset();
+
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let z = builder.wire();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+        let circuit: WrappedCircuit<Bn128F, Bn256, Linting> =
+            WrappedCircuit::new(builder.build(), Default::default(), vec![z]);
+
+        let minus_five = Bn128F::order() - BigUint::from(5u8);
+        let mid = Bn128F::order() >> 1;
+        let mut values = WireValues::new();
+        values.set(x, Element::from(minus_five.clone()));
+        values.set(y, Element::from(mid));
+        values.set(z, Element::from(3u8));
+        let witness_map = circuit.witness_from_wire_values(&values);
+        assert_eq!(witness_map.len(), 3);
+        assert_eq!(Linting::possible_negatives(), vec![minus_five]);
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::{BTreeMap, HashSet};
+use std::marker::PhantomData;
+use bellman::{Circuit, ConstraintSystem, SynthesisError};
+use pairing::Engine;
+use r1cs::{Constraint, Field, Wire};
+
+use crate::{FieldConverter, WrappedCircuit};
+
+/// Like `WrappedCircuit`, but borrows its constraints and witness rather than owning them, so that
+/// large constraint sets, such as ones generated into an arena, needn't be cloned for each
+/// synthesis.
+pub struct WrappedCircuitRef<'a, F: Field, E: Engine, C: FieldConverter<F, E>> {
+    constraints: &'a [Constraint<F>],
+    witness_map: &'a BTreeMap<u32, E::Fr>,
+    public_inputs: &'a [Wire],
+    _c: PhantomData<C>,
+}
+
+impl<'a, F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuitRef<'a, F, E, C> {
+    pub fn new(
+        constraints: &'a [Constraint<F>],
+        witness_map: &'a BTreeMap<u32, E::Fr>,
+        public_inputs: &'a [Wire]
+    ) -> Self {
+        assert!(C::is_compatible(), "the field converter doesn't support this field and engine");
+        Self { constraints, witness_map, public_inputs, _c: PhantomData }
+    }
+}
+
+impl<'a, F: Field, E: Engine, C: FieldConverter<F, E>> Circuit<E> for WrappedCircuitRef<'a, F, E, C> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let public_inputs: HashSet<Wire> = self.public_inputs.iter().cloned().collect();
+        WrappedCircuit::<F, E, C>::_synthesize_constraints(
+            cs, self.constraints, self.witness_map, &public_inputs, &[]);
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use bellman::groth16::{create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof};
+    use pairing::compact_bn256::Bn256;
+    use r1cs::{Expression, GadgetBuilder, Bn128 as Bn128F};
+    use rand::thread_rng;
+
+    use crate::{Bn256Converter, WrappedCircuitRef};
+    use crate::test_util::{fr, product_witness};
+
+    #[test]
+    fn prove_from_borrowed_constraints() {
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let z = builder.wire();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+        let constraints = builder.build().constraints;
+        let public_inputs = [z];
+
+        let rng = &mut thread_rng();
+        let empty_map = BTreeMap::new();
+        let circuit = WrappedCircuitRef::<_, Bn256, Bn256Converter>::new(&constraints, &empty_map, &public_inputs);
+        let params = generate_random_parameters(circuit, rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+
+        let witness_map = product_witness(2, 3, 6);
+        let circuit = WrappedCircuitRef::<_, Bn256, Bn256Converter>::new(&constraints, &witness_map, &public_inputs);
+        let proof = create_random_proof(circuit, &params, rng).unwrap();
+        assert!(verify_proof(&pvk, &proof, &[fr(6)]).unwrap());
+
+        // The caller still owns the constraints.
+        assert_eq!(constraints.len(), 1);
+    }
+}
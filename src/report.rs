@@ -0,0 +1,102 @@
+use std::fmt::Write;
+use pairing::Engine;
+use r1cs::{Element, Expression, Field, Wire};
+
+use crate::{FieldConverter, WrappedCircuit};
+
+/// The number of constraints shown at each end of a report before the middle is elided.
+pub const REPORT_EDGE_CONSTRAINTS: usize = 10;
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Formats the constraint at `index` as `(A) * (B) = (C)`.
+    pub fn dump_constraint(&self, index: usize) -> String {
+        let constraint = &self.gadget().constraints[index];
+        format!("({}) * ({}) = ({})",
+                format_expression(&constraint.a),
+                format_expression(&constraint.b),
+                format_expression(&constraint.c))
+    }
+
+    /// Formats the whole circuit: a summary header followed by each constraint. For circuits with
+    /// more than `2 * REPORT_EDGE_CONSTRAINTS` constraints, only the first and last
+    /// `REPORT_EDGE_CONSTRAINTS` are shown.
+    pub fn to_report(&self) -> String {
+        let num_constraints = self.gadget().constraints.len();
+        let public_inputs: Vec<String> = self.public_inputs().iter().map(|w| wire_label(*w)).collect();
+        let mut report = String::new();
+        writeln!(report, "constraints: {}", num_constraints).unwrap();
+        writeln!(report, "public inputs: {} ({})", public_inputs.len(), public_inputs.join(", ")).unwrap();
+
+        let elided = num_constraints > 2 * REPORT_EDGE_CONSTRAINTS;
+        for i in 0..num_constraints {
+            if elided && i == REPORT_EDGE_CONSTRAINTS {
+                writeln!(report, "... {} constraints omitted ...",
+                         num_constraints - 2 * REPORT_EDGE_CONSTRAINTS).unwrap();
+            }
+            if elided && i >= REPORT_EDGE_CONSTRAINTS && i < num_constraints - REPORT_EDGE_CONSTRAINTS {
+                continue;
+            }
+            writeln!(report, "{}: {}", i, self.dump_constraint(i)).unwrap();
+        }
+        report
+    }
+}
+
+/// Formats an expression as a sum of terms like `3 * w2`, omitting unit coefficients.
+pub(crate) fn format_expression<F: Field>(exp: &Expression<F>) -> String {
+    if exp.coefficients().is_empty() {
+        return "0".to_string();
+    }
+    let terms: Vec<String> = exp.coefficients().iter()
+        .map(|(wire, coeff)| {
+            if *coeff == Element::one() {
+                wire_label(*wire)
+            } else {
+                format!("{} * {}", coeff.to_biguint(), wire_label(*wire))
+            }
+        })
+        .collect();
+    terms.join(" + ")
+}
+
+/// Labels the constant wire `1`, and others `w<index>`.
+pub(crate) fn wire_label(wire: Wire) -> String {
+    if wire == Wire::ONE {
+        "1".to_string()
+    } else {
+        format!("w{}", wire.index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use r1cs::{Expression, GadgetBuilder, Bn128 as Bn128F};
+
+    use crate::WrappedCircuit;
+    use crate::test_util::{product_circuit, ProductCircuit};
+
+    #[test]
+    fn product_circuit_report() {
+        let report = product_circuit(BTreeMap::new()).to_report();
+        assert!(report.contains("constraints: 1\n"));
+        assert!(report.contains("public inputs: 1 (w3)\n"));
+        assert!(report.contains("0: (w1) * (w2) = (w3)\n"));
+    }
+
+    #[test]
+    fn large_report_is_truncated() {
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        for _ in 0..30 {
+            builder.assert_product(&Expression::from(&x), &Expression::from(&x), &Expression::from(&x));
+        }
+        let circuit: ProductCircuit = WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![]);
+        let report = circuit.to_report();
+        assert!(report.contains("constraints: 30\n"));
+        assert!(report.contains("9: "));
+        assert!(report.contains("... 10 constraints omitted ...\n"));
+        assert!(!report.contains("\n15: "));
+        assert!(report.contains("29: "));
+    }
+}
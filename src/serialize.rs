@@ -0,0 +1,89 @@
+//! A canonical byte layout for public inputs, for verifiers written in other languages.
+//!
+//! The layout is a 4-byte little-endian count, followed by each element's canonical integer
+//! representation (not its Montgomery form) in little-endian byte order. Each element takes the
+//! full width of the field's repr, which is 32 bytes for both BN256 and BLS12-381.
+
+use std::io::{self, Read, Write};
+use pairing::ff::{PrimeField, PrimeFieldRepr};
+
+/// Serializes public inputs in the canonical layout.
+pub fn serialize_public_inputs<Fr: PrimeField>(inputs: &[Fr]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_public_inputs(inputs, &mut bytes).expect("writing to a Vec can't fail");
+    bytes
+}
+
+/// Deserializes public inputs from the canonical layout, rejecting trailing bytes and elements
+/// which aren't less than the modulus.
+pub fn deserialize_public_inputs<Fr: PrimeField>(mut bytes: &[u8]) -> io::Result<Vec<Fr>> {
+    let inputs = read_public_inputs(&mut bytes)?;
+    if !bytes.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "trailing bytes after public inputs"));
+    }
+    Ok(inputs)
+}
+
+pub fn write_public_inputs<Fr: PrimeField, W: Write>(inputs: &[Fr], mut writer: W) -> io::Result<()> {
+    writer.write_all(&(inputs.len() as u32).to_le_bytes())?;
+    for input in inputs {
+        input.into_repr().write_le(&mut writer)?;
+    }
+    Ok(())
+}
+
+pub fn read_public_inputs<Fr: PrimeField, R: Read>(mut reader: R) -> io::Result<Vec<Fr>> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let mut inputs = Vec::new();
+    for _ in 0..u32::from_le_bytes(len) {
+        let mut repr = Fr::Repr::default();
+        repr.read_le(&mut reader)?;
+        let input = Fr::from_repr(repr)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        inputs.push(input);
+    }
+    Ok(inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use pairing::bls12_381::Fr;
+    use pairing::ff::{Field, PrimeField};
+
+    use crate::{deserialize_public_inputs, serialize_public_inputs};
+
+    #[test]
+    fn round_trip() {
+        let inputs = vec![Fr::zero(), Fr::one(), Fr::from_str("123456789").unwrap(), Fr::root_of_unity()];
+        let bytes = serialize_public_inputs(&inputs);
+        assert_eq!(bytes.len(), 4 + 32 * inputs.len());
+        assert_eq!(deserialize_public_inputs::<Fr>(&bytes).unwrap(), inputs);
+
+        assert!(deserialize_public_inputs::<Fr>(&bytes[..bytes.len() - 1]).is_err());
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(deserialize_public_inputs::<Fr>(&trailing).is_err());
+    }
+
+    #[test]
+    fn known_bls12_381_bytes() {
+        let inputs = [Fr::one(), Fr::from_str("258").unwrap()];
+        let mut expected = vec![2, 0, 0, 0];
+        expected.push(1);
+        expected.extend_from_slice(&[0; 31]);
+        expected.extend_from_slice(&[2, 1]);
+        expected.extend_from_slice(&[0; 30]);
+        assert_eq!(serialize_public_inputs(&inputs), expected);
+    }
+
+    #[test]
+    fn rejects_non_canonical_elements() {
+        // The BLS12-381 scalar field modulus itself, which is not a canonical element.
+        let mut bytes = vec![1, 0, 0, 0];
+        let mut modulus = Vec::new();
+        pairing::ff::PrimeFieldRepr::write_le(&Fr::char(), &mut modulus).unwrap();
+        bytes.extend_from_slice(&modulus);
+        assert!(deserialize_public_inputs::<Fr>(&bytes).is_err());
+    }
+}
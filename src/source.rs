@@ -0,0 +1,80 @@
+use std::collections::BTreeMap;
+use pairing::Engine;
+use r1cs::{Field, Gadget, Wire};
+
+use crate::{witness_key, witness_of, FieldConverter, WrappedCircuit};
+
+/// Supplies witness values to synthesis, one wire at a time. Wires without a value are assigned
+/// zero.
+pub trait WitnessSource<Fr> {
+    fn witness(&self, wire: Wire) -> Option<Fr>;
+}
+
+impl<Fr: Copy> WitnessSource<Fr> for BTreeMap<u32, Fr> {
+    fn witness(&self, wire: Wire) -> Option<Fr> {
+        witness_of(self, wire).cloned()
+    }
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Builds a circuit whose witness comes from two sources: `public` for the public input wires,
+    /// and `private` for every other wire. Each wire is only looked up in the source for its kind,
+    /// so a value which one source has for a wire of the other kind is ignored, rather than
+    /// silently used.
+    pub fn with_split_witness<P, Q>(
+        gadget: Gadget<F>,
+        public_inputs: Vec<Wire>,
+        public: &P,
+        private: &Q
+    ) -> Self
+    where
+        P: WitnessSource<E::Fr> + ?Sized,
+        Q: WitnessSource<E::Fr> + ?Sized,
+    {
+        let mut circuit = Self::new(gadget, BTreeMap::new(), public_inputs);
+        let public_wires = circuit.public_inputs().clone();
+        let private_wires = circuit.referenced_wires().into_iter()
+            .filter(|wire| !public_wires.contains(wire))
+            .collect::<Vec<_>>();
+        let public_values = public_wires.iter().map(|wire| (*wire, public.witness(*wire)));
+        let private_values = private_wires.iter().map(|wire| (*wire, private.witness(*wire)));
+        circuit.witness_map = public_values.chain(private_values)
+            .filter_map(|(wire, value)| Some((witness_key(wire), value?)))
+            .collect();
+        circuit
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use r1cs::{Expression, GadgetBuilder, Bn128 as Bn128F};
+    use rand::thread_rng;
+
+    use crate::{Groth16Prover, WrappedCircuit};
+    use crate::test_util::{fr, product_circuit, ProductCircuit};
+
+    #[test]
+    fn public_and_private_witness_sources() {
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let z = builder.wire();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+
+        // Each source also has a stray value for a wire of the other kind, which must be ignored.
+        let public: BTreeMap<u32, _> = vec![(z.index, fr(6)), (x.index, fr(5))].into_iter().collect();
+        let private: BTreeMap<u32, _> =
+            vec![(x.index, fr(2)), (y.index, fr(3)), (z.index, fr(7))].into_iter().collect();
+        let circuit: ProductCircuit =
+            WrappedCircuit::with_split_witness(builder.build(), vec![z], &public, &private);
+        let expected: BTreeMap<u32, _> = vec![(1, fr(2)), (2, fr(3)), (3, fr(6))].into_iter().collect();
+        assert_eq!(circuit.witness_map(), &expected);
+        assert_eq!(circuit.check_satisfied(), Ok(()));
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&product_circuit(BTreeMap::new()), rng).unwrap();
+        let proof = prover.prove(circuit.witness_map().clone(), rng).unwrap();
+        assert!(prover.verify(&proof, &circuit.public_input_values()).unwrap());
+    }
+}
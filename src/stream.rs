@@ -0,0 +1,168 @@
+//! A line-based variant of the JSON export, for circuits too large to hold in memory at once.
+//!
+//! The first line is a header object, with the format version and the public inputs as in
+//! `to_json`. Each following line is one constraint, in the same form as the elements of
+//! `to_json`'s constraint list.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use bellman::{Circuit, ConstraintSystem, SynthesisError};
+use pairing::Engine;
+use r1cs::{Field, Wire};
+use serde_json::{json, Value};
+
+use crate::json::{constraint_from_json, constraint_to_json, parse_index};
+use crate::{Error, FieldConverter, MappedWitness, WitnessSource, WrappedCircuit, JSON_FORMAT_VERSION};
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Writes the circuit's constraints and public inputs in the streaming format. As with
+    /// `to_json`, raw and conditional constraints and the wire ordering are not included, and
+    /// neither is the witness.
+    pub fn write_constraint_stream<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let public_inputs: Vec<u32> = self.public_inputs().iter().map(|wire| wire.index).collect();
+        let header = json!({ "version": JSON_FORMAT_VERSION, "public_inputs": public_inputs });
+        writeln!(writer, "{}", header)?;
+        for constraint in &self.gadget().constraints {
+            writeln!(writer, "{}", constraint_to_json(constraint))?;
+        }
+        Ok(())
+    }
+}
+
+/// A circuit whose constraints are read from a file in the streaming format during synthesis, one
+/// at a time, and whose witness, if any, is read from a memory-mapped witness buffer. Neither is
+/// ever held in memory in full.
+///
+/// Wires are allocated as they are first referenced, as a `WrappedCircuit` with the default
+/// `FirstUseOrder` allocates them, so public inputs must be verified in first-use order.
+pub struct StreamedCircuit<F: Field, E: Engine, C: FieldConverter<F, E>> {
+    path: PathBuf,
+    public_inputs: Vec<Wire>,
+    witness: Option<MappedWitness<E::Fr>>,
+    _c: PhantomData<(F, C)>,
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> StreamedCircuit<F, E, C> {
+    /// Opens a constraint stream, reading only its header. The constraints are read, and
+    /// validated, on each synthesis.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        assert!(C::is_compatible(), "the field converter doesn't support this field and engine");
+        let mut header = String::new();
+        BufReader::new(File::open(&path)?).read_line(&mut header)?;
+        let header: Value = serde_json::from_str(&header)
+            .map_err(|e| Error::InvalidFormat(e.to_string()))?;
+        if header["version"].as_u64() != Some(JSON_FORMAT_VERSION) {
+            return Err(Error::InvalidFormat("unsupported version".to_string()));
+        }
+        let public_inputs = header["public_inputs"].as_array()
+            .ok_or_else(|| Error::InvalidFormat("missing public inputs".to_string()))?
+            .iter()
+            .map(|index| Ok(Wire { index: parse_index(index)? }))
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(Self { path: path.as_ref().to_path_buf(), public_inputs, witness: None, _c: PhantomData })
+    }
+
+    /// Uses a memory-mapped witness for proving. Without one, every wire is assigned zero, which
+    /// suffices for parameter generation.
+    pub fn with_witness(mut self, witness: MappedWitness<E::Fr>) -> Self {
+        self.witness = Some(witness);
+        self
+    }
+
+    pub fn public_inputs(&self) -> &[Wire] {
+        &self.public_inputs
+    }
+}
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> Circuit<E> for StreamedCircuit<F, E, C> {
+    fn synthesize<CS: ConstraintSystem<E>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let public_inputs: HashSet<Wire> = self.public_inputs.iter().cloned().collect();
+        let no_witness = BTreeMap::new();
+        let witness: &dyn WitnessSource<E::Fr> = match &self.witness {
+            Some(witness) => witness,
+            None => &no_witness,
+        };
+        let invalid = |reason: String| {
+            SynthesisError::IoError(io::Error::new(io::ErrorKind::InvalidData, reason))
+        };
+
+        let mut variable_map = BTreeMap::new();
+        let lines = BufReader::new(File::open(&self.path)?).lines().skip(1);
+        for (i, line) in lines.enumerate() {
+            let value: Value = serde_json::from_str(&line?).map_err(|e| invalid(e.to_string()))?;
+            let constraint = constraint_from_json::<F>(&value).map_err(|e| invalid(e.to_string()))?;
+            WrappedCircuit::<F, E, C>::_enforce_constraint(
+                cs, i, &constraint, &mut variable_map, witness, &public_inputs);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs::{self, File};
+    use bellman::Circuit;
+    use bellman::groth16::{create_random_proof, generate_random_parameters, prepare_verifying_key, verify_proof};
+    use pairing::compact_bn256::{Bn256, Fr};
+    use pairing::ff::Field;
+    use r1cs::{Expression, GadgetBuilder, Bn128 as Bn128F};
+    use rand::thread_rng;
+
+    use crate::{serialize_public_inputs, Bn256Converter, CollectingConstraintSystem, MappedWitness};
+    use crate::{StreamedCircuit, WrappedCircuit};
+    use crate::test_util::{fr, ProductCircuit};
+
+    type Streamed = StreamedCircuit<Bn128F, Bn256, Bn256Converter>;
+
+    #[test]
+    fn prove_streamed_from_disk() {
+        // A chain of squarings, w[i + 1] = w[i]^2, with the last wire public.
+        const N: usize = 256;
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let w = builder.wires(N + 1);
+        for i in 0..N {
+            let exp = Expression::from(&w[i]);
+            builder.assert_product(&exp, &exp, &Expression::from(&w[i + 1]));
+        }
+        let mut values = vec![Fr::one(), fr(3)];
+        for _ in 0..N {
+            let mut next = *values.last().unwrap();
+            next.square();
+            values.push(next);
+        }
+        let witness_map: BTreeMap<u32, Fr> = values.iter().cloned().enumerate().skip(1)
+            .map(|(index, value)| (index as u32, value))
+            .collect();
+        let circuit: ProductCircuit = WrappedCircuit::new(builder.build(), witness_map, vec![w[N]]);
+
+        let dir = std::env::temp_dir().join(format!("r1cs-bellman-stream-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let constraints_path = dir.join("constraints.jsonl");
+        let witness_path = dir.join("witness.bin");
+        circuit.write_constraint_stream(File::create(&constraints_path).unwrap()).unwrap();
+        fs::write(&witness_path, serialize_public_inputs(&values)).unwrap();
+
+        let streamed = Streamed::open(&constraints_path).unwrap();
+        assert_eq!(streamed.public_inputs(), &[w[N]]);
+        let mut cs = CollectingConstraintSystem::<Bn256>::new();
+        streamed.synthesize(&mut cs).unwrap();
+        assert_eq!(cs.matrices(), circuit.to_matrices());
+
+        let rng = &mut thread_rng();
+        let streamed = Streamed::open(&constraints_path).unwrap();
+        let params = generate_random_parameters::<Bn256, _, _>(streamed, rng).unwrap();
+        let witness = MappedWitness::<Fr>::open(&witness_path).unwrap();
+        assert_eq!(witness.num_wires(), N + 2);
+        let streamed = Streamed::open(&constraints_path).unwrap().with_witness(witness);
+        let proof = create_random_proof(streamed, &params, rng).unwrap();
+        let pvk = prepare_verifying_key(&params.vk);
+        assert!(verify_proof(&pvk, &proof, &circuit.public_input_values()).unwrap());
+        assert!(!verify_proof(&pvk, &proof, &[fr(3)]).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::BTreeMap;
+use pairing::compact_bn256::{Bn256, Fr};
+use r1cs::{Constraint, Element, Expression, GadgetBuilder, Wire, Bn128 as Bn128F};
+
+use crate::{Bn256Converter, FieldConverter, WrappedCircuit};
+
+pub type ProductCircuit = WrappedCircuit<Bn128F, Bn256, Bn256Converter>;
+
+/// Builds a circuit asserting `x * y = z`, with `z` as its only public input.
+pub fn product_circuit(witness_map: BTreeMap<u32,Fr>) -> ProductCircuit {
+    let mut builder = GadgetBuilder::<Bn128F>::new();
+    let x = builder.wire();
+    let y = builder.wire();
+    let z = builder.wire();
+    builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+    let gadget = builder.build();
+    WrappedCircuit::new(gadget, witness_map, vec![z])
+}
+
+/// Builds a witness map for `product_circuit` assigning `x`, `y` and `z`.
+pub fn product_witness(x: u8, y: u8, z: u8) -> BTreeMap<u32,Fr> {
+    let mut witness_map = BTreeMap::new();
+    witness_map.insert(1, fr(x));
+    witness_map.insert(2, fr(y));
+    witness_map.insert(3, fr(z));
+    witness_map
+}
+
+/// Builds a circuit asserting `x * y = z`, and `x * x = w` when `s` is nonzero, with `z` as its
+/// only public input.
+pub fn conditional_circuit(witness_map: BTreeMap<u32,Fr>) -> ProductCircuit {
+    let mut builder = GadgetBuilder::<Bn128F>::new();
+    let w = builder.wires(5);
+    let (x, y, z) = (w[0], w[1], w[2]);
+    builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+    let square = Constraint {
+        a: Expression::from(&x),
+        b: Expression::from(&x),
+        c: Expression::from(&w[3]),
+    };
+    WrappedCircuit::new(builder.build(), witness_map, vec![z]).with_conditional_constraint(square, w[4])
+}
+
+/// Builds a witness map for `conditional_circuit` assigning `x`, `y`, `z`, `w` and `s`.
+pub fn conditional_witness(x: u8, y: u8, z: u8, w: u8, s: u8) -> BTreeMap<u32,Fr> {
+    let mut witness_map = product_witness(x, y, z);
+    witness_map.insert(4, fr(w));
+    witness_map.insert(5, fr(s));
+    witness_map
+}
+
+pub fn fr(n: u8) -> Fr {
+    Bn256Converter::convert_field(&Element::from(n))
+}
+
+/// Builds the expression `coeff * wire`.
+pub fn scaled(wire: Wire, coeff: Element<Bn128F>) -> Expression<Bn128F> {
+    let mut coefficients = BTreeMap::new();
+    coefficients.insert(wire, coeff);
+    Expression::new(coefficients)
+}
@@ -0,0 +1,160 @@
+use bellman::SynthesisError;
+use bellman::groth16::{prepare_verifying_key, verify_proof, PreparedVerifyingKey, Proof, VerifyingKey};
+use pairing::{CurveAffine, CurveProjective, EncodedPoint, Engine};
+use pairing::ff::PrimeField;
+
+use crate::FormatError;
+
+/// Returns the length of a proof serialized by `Proof::write`: two compressed G1 points and a
+/// compressed G2 point.
+pub fn proof_length<E: Engine>() -> usize {
+    2 * <<E::G1Affine as CurveAffine>::Compressed as EncodedPoint>::size()
+        + <<E::G2Affine as CurveAffine>::Compressed as EncodedPoint>::size()
+}
+
+/// Cheaply checks that `proof_bytes` is a well formed proof for `E`, so that malformed proofs can
+/// be rejected before the pairing check. The length is checked first, and then each point is
+/// decompressed and checked to be on the curve and in the right subgroup. A proof passing this can
+/// still fail to verify.
+pub fn validate_proof_format<E: Engine>(proof_bytes: &[u8]) -> Result<(), FormatError> {
+    let expected = proof_length::<E>();
+    if proof_bytes.len() != expected {
+        return Err(FormatError::WrongLength { expected, actual: proof_bytes.len() });
+    }
+    Proof::<E>::read(proof_bytes).map(|_| ()).map_err(|_| FormatError::InvalidPoint)
+}
+
+/// Verifies proofs against a verifying key which is prepared once, at construction, rather than
+/// for every verification.
+pub struct CachedVerifier<E: Engine> {
+    pvk: PreparedVerifyingKey<E>,
+}
+
+impl<E: Engine> CachedVerifier<E> {
+    pub fn new(vk: &VerifyingKey<E>) -> Self {
+        Self { pvk: prepare_verifying_key(vk) }
+    }
+
+    pub fn prepared_verifying_key(&self) -> &PreparedVerifyingKey<E> {
+        &self.pvk
+    }
+
+    pub fn verify(&self, proof: &Proof<E>, public_inputs: &[E::Fr]) -> Result<bool, SynthesisError> {
+        verify_proof(&self.pvk, proof, public_inputs)
+    }
+}
+
+/// Computes the G1 point which a Groth16 verifier derives from the public inputs and pairs with
+/// `gamma`: `ic[0] + x_1 ic[1] + ... + x_n ic[n]`, for the verifying key's `ic` and the public
+/// inputs `x_i` in order. It is a binding commitment to the inputs under that key, which protocols
+/// can hash or sign before verifying; its canonical encoding is `into_compressed()`, as used by
+/// `Proof::write`.
+///
+/// Fails, as `verify_proof` does, if the number of inputs doesn't match the key.
+pub fn public_input_commitment<E: Engine>(
+    vk: &VerifyingKey<E>,
+    public_inputs: &[E::Fr]
+) -> Result<E::G1Affine, SynthesisError> {
+    if public_inputs.len() + 1 != vk.ic.len() {
+        return Err(SynthesisError::MalformedVerifyingKey);
+    }
+    let mut acc = vk.ic[0].into_projective();
+    for (input, base) in public_inputs.iter().zip(&vk.ic[1..]) {
+        acc.add_assign(&base.mul(input.into_repr()));
+    }
+    Ok(acc.into_affine())
+}
+
+/// Returns the index of the first of `candidates` which `proof` is valid for, or `None` if it is
+/// valid for none of them. Candidates after the first match are not checked. A malformed candidate,
+/// such as one of the wrong length, counts as a failure.
+pub fn verify_any<E: Engine>(
+    pvk: &PreparedVerifyingKey<E>,
+    proof: &Proof<E>,
+    candidates: &[Vec<E::Fr>]
+) -> Option<usize> {
+    candidates.iter().position(|public_inputs| {
+        verify_proof(pvk, proof, public_inputs).unwrap_or(false)
+    })
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use bellman::SynthesisError;
+    use pairing::CurveAffine;
+    use pairing::compact_bn256::Bn256;
+    use rand::thread_rng;
+
+
+    use crate::{proof_length, public_input_commitment, validate_proof_format, verify_any};
+    use crate::{CachedVerifier, FormatError, Groth16Prover};
+    use crate::test_util::{fr, product_circuit, product_witness};
+
+    #[test]
+    fn second_candidate_verifies() {
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&product_circuit(BTreeMap::new()), rng).unwrap();
+        let proof = prover.prove(product_witness(2, 3, 6), rng).unwrap();
+
+        let pvk = prover.prepared_verifying_key();
+        let candidates = vec![vec![fr(5)], vec![fr(6)], vec![fr(7)]];
+        assert_eq!(verify_any(pvk, &proof, &candidates), Some(1));
+        assert_eq!(verify_any(pvk, &proof, &candidates[2..]), None);
+    }
+
+    #[test]
+    fn cached_verifier_checks_many_proofs() {
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&product_circuit(BTreeMap::new()), rng).unwrap();
+        let verifier = CachedVerifier::new(&prover.parameters().vk);
+        for x in 1..6u8 {
+            let proof = prover.prove(product_witness(x, 3, x * 3), rng).unwrap();
+            assert!(verifier.verify(&proof, &[fr(x * 3)]).unwrap());
+            assert!(!verifier.verify(&proof, &[fr(x * 3 + 1)]).unwrap());
+        }
+    }
+
+    #[test]
+    fn malformed_proofs_are_rejected() {
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&product_circuit(BTreeMap::new()), rng).unwrap();
+        let proof = prover.prove(product_witness(2, 3, 6), rng).unwrap();
+        let mut bytes = vec![];
+        proof.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), proof_length::<Bn256>());
+        assert_eq!(validate_proof_format::<Bn256>(&bytes), Ok(()));
+
+        let expected = bytes.len();
+        assert_eq!(validate_proof_format::<Bn256>(&bytes[..expected - 1]),
+            Err(FormatError::WrongLength { expected, actual: expected - 1 }));
+
+        // An x coordinate of all ones is larger than the modulus, so it can't be a point.
+        let mut invalid = bytes.clone();
+        for byte in &mut invalid[..32] {
+            *byte = 0xff;
+        }
+        assert_eq!(validate_proof_format::<Bn256>(&invalid), Err(FormatError::InvalidPoint));
+    }
+
+    #[test]
+    fn commitment_depends_on_every_input() {
+        let (circuit, _) = product_circuit(BTreeMap::new()).with_context_input();
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        let vk = &prover.parameters().vk;
+
+        let commitment = public_input_commitment(vk, &[fr(6), fr(1)]).unwrap();
+        assert_eq!(public_input_commitment(vk, &[fr(6), fr(1)]).unwrap(), commitment);
+        assert_ne!(public_input_commitment(vk, &[fr(7), fr(1)]).unwrap(), commitment);
+        assert_ne!(public_input_commitment(vk, &[fr(6), fr(2)]).unwrap(), commitment);
+        assert_ne!(public_input_commitment(vk, &[fr(1), fr(6)]).unwrap(), commitment);
+        assert_eq!(public_input_commitment(vk, &[fr(0), fr(0)]).unwrap(), vk.ic[0]);
+        assert!(!commitment.is_zero());
+
+        match public_input_commitment(vk, &[fr(6)]) {
+            Err(SynthesisError::MalformedVerifyingKey) => {}
+            _ => panic!("expected a malformed verifying key error"),
+        }
+    }
+}
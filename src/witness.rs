@@ -0,0 +1,322 @@
+use std::collections::{BTreeMap, BTreeSet};
+use num::BigUint;
+use pairing::Engine;
+use pairing::ff::{Field as _, PrimeField};
+use r1cs::{Element, Expression, Field, Gadget, Wire, WireValues};
+
+use crate::{biguint_from_repr, element_from_fr, engine_modulus, fr_from_biguint, wire_for_key};
+use crate::{witness_key, witness_of};
+use crate::{Error, FieldConverter, ModulusCheck, WrappedCircuit};
+
+impl<F: Field, E: Engine, C: FieldConverter<F, E>> WrappedCircuit<F, E, C> {
+    /// Like `new`, but takes the witness as raw reprs in the engine's internal Montgomery form, as
+    /// returned by `into_raw_repr`, and uses them as is, with no conversion.
+    ///
+    /// Values are only checked to be below the modulus. The caller must ensure they really are the
+    /// Montgomery form of the intended elements; a canonical repr passed here silently denotes a
+    /// different element, giving a witness which doesn't satisfy the constraints.
+    pub fn from_montgomery_witness(
+        gadget: Gadget<F>,
+        witness: BTreeMap<u32, <E::Fr as PrimeField>::Repr>,
+        public_inputs: Vec<Wire>
+    ) -> Result<Self, Error> {
+        let mut witness_map = BTreeMap::new();
+        for (index, repr) in witness {
+            let fr = E::Fr::from_raw_repr(repr).map_err(|_| Error::ElementOutOfRange {
+                value: biguint_from_repr(&repr),
+                modulus: engine_modulus::<E>(),
+            })?;
+            witness_map.insert(index, fr);
+        }
+        Ok(Self::new(gadget, witness_map, public_inputs))
+    }
+
+    /// Returns every wire referenced by the circuit's constraints, including conditional constraints
+    /// and their selectors, other than the constant wire.
+    pub fn referenced_wires(&self) -> BTreeSet<Wire> {
+        let mut wires = BTreeSet::new();
+        let conditionals = self.conditional_constraints().iter().map(|conditional| &conditional.constraint);
+        for constraint in self.gadget().constraints.iter().chain(conditionals) {
+            for exp in &[&constraint.a, &constraint.b, &constraint.c] {
+                wires.extend(exp.coefficients().keys().filter(|wire| **wire != Wire::ONE));
+            }
+        }
+        wires.extend(self.conditional_constraints().iter()
+            .map(|conditional| conditional.selector)
+            .filter(|wire| *wire != Wire::ONE));
+        wires
+    }
+
+    /// Builds a witness map from wire values computed by r1cs, such as by `Gadget::execute`,
+    /// covering every wire the circuit references which `values` assigns.
+    pub fn witness_from_wire_values(&self, values: &WireValues<F>) -> BTreeMap<u32, E::Fr> {
+        self.referenced_wires().into_iter()
+            .filter(|wire| values.contains(*wire))
+            .map(|wire| (witness_key(wire), C::convert_field(values.get(wire))))
+            .collect()
+    }
+
+    /// Converts the witness map back into r1cs wire values, for cross-checking with r1cs's own
+    /// tooling. Fails if a value doesn't fit in `F`. Any witness for the constant wire is
+    /// ignored, since r1cs always assigns it one.
+    pub fn wire_values_from_witness(&self) -> Result<WireValues<F>, Error> {
+        let mut values = WireValues::new();
+        for (index, value) in self.witness_map() {
+            let wire = wire_for_key(*index);
+            if wire != Wire::ONE {
+                values.set(wire, element_from_fr(value)?);
+            }
+        }
+        Ok(values)
+    }
+
+    /// Returns every wire the constraints reference which has no witness, other than the constant
+    /// wire. Public inputs are skipped unless `include_public_inputs` is set, for callers which
+    /// only need the private part of the witness, with the verifier supplying the rest.
+    pub fn missing_witnesses(&self, include_public_inputs: bool) -> Vec<Wire> {
+        self.referenced_wires().into_iter()
+            .filter(|wire| include_public_inputs || !self.public_inputs().contains(wire))
+            .filter(|wire| witness_of(self.witness_map(), *wire).is_none())
+            .collect()
+    }
+
+    /// Checks that every wire the constraints reference, other than the constant wire, has a
+    /// witness, skipping public inputs unless `include_public_inputs` is set, as with
+    /// `missing_witnesses`. Otherwise, returns all of the wires which lack one.
+    pub fn require_full_witness(&self, include_public_inputs: bool) -> Result<(), Vec<Wire>> {
+        let missing = self.missing_witnesses(include_public_inputs);
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(missing)
+        }
+    }
+}
+
+/// Runs a (typically small) gadget to compute a public input vector, as a verifier would when it
+/// derives the public inputs itself rather than receiving them. `values` should assign the
+/// gadget's inputs; the gadget's witness generators fill in the rest. Returns `None` if the
+/// gadget's constraints are not satisfied.
+pub fn public_inputs_from_gadget<F: Field, E: Engine, C: FieldConverter<F, E>>(
+    gadget: &Gadget<F>,
+    values: &mut WireValues<F>,
+    outputs: &[Expression<F>]
+) -> Option<Vec<E::Fr>> {
+    if !gadget.execute(values) {
+        return None;
+    }
+    Some(outputs.iter().map(|output| C::convert_field(&output.evaluate(values))).collect())
+}
+
+/// Converts a gadget's public outputs, in the order the verifier expects them, into public inputs
+/// for verification, checking that each fits in `E::Fr`. The verifier needs nothing else from the
+/// circuit.
+pub fn public_inputs_for_verification<F: Field, E: Engine, C: FieldConverter<F, E>>(
+    outputs: &[Element<F>]
+) -> Result<Vec<E::Fr>, Error> {
+    let check = ModulusCheck::for_engine::<E>();
+    outputs.iter().map(|output| check.convert::<F, E, C>(output)).collect()
+}
+
+/// Builds a witness map from signed integers, embedding each into `Fr`: nonnegative values as
+/// themselves, and negative values `-n` as `modulus - n`, the additive inverse of `n`.
+///
+/// Panics if `Fr` can't hold 128 bit integers, which every supported engine can.
+pub fn witness_from_signed_map<Fr: PrimeField>(m: &BTreeMap<u32, i128>) -> BTreeMap<u32, Fr> {
+    m.iter()
+        .map(|(key, n)| {
+            let mut value: Fr = fr_from_biguint(&BigUint::from(n.unsigned_abs()))
+                .expect("the field is too small for 128 bit integers");
+            if *n < 0 {
+                value.negate();
+            }
+            (*key, value)
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use pairing::compact_bn256::{Bn256, Fr};
+    use pairing::ff::{Field as _, PrimeField};
+    use r1cs::{Element, Expression, GadgetBuilder, WireValues, Bn128 as Bn128F};
+    use rand::thread_rng;
+
+    use crate::{Bn256Converter, Groth16Prover, WrappedCircuit, public_inputs_for_verification};
+    use crate::{public_inputs_from_gadget, witness_from_signed_map};
+    use crate::test_util::{fr, product_circuit, product_witness, ProductCircuit};
+
+    #[test]
+    fn verifier_derives_public_inputs() {
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&product_circuit(BTreeMap::new()), rng).unwrap();
+        let proof = prover.prove(product_witness(2, 3, 6), rng).unwrap();
+
+        // The verifier knows x and y, and derives z = x * y itself.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let z = builder.product(&Expression::from(&x), &Expression::from(&y));
+        let gadget = builder.build();
+
+        let mut values = WireValues::new();
+        values.set(x, Element::from(2u8));
+        values.set(y, Element::from(3u8));
+        let public_inputs = public_inputs_from_gadget::<Bn128F, Bn256, Bn256Converter>(
+            &gadget, &mut values, &[z]).unwrap();
+        assert_eq!(public_inputs, vec![fr(6)]);
+        assert!(prover.verify(&proof, &public_inputs).unwrap());
+    }
+
+    #[test]
+    fn public_inputs_from_elements() {
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&product_circuit(BTreeMap::new()), rng).unwrap();
+        let proof = prover.prove(product_witness(2, 3, 6), rng).unwrap();
+
+        let public_inputs = public_inputs_for_verification::<Bn128F, Bn256, Bn256Converter>(
+            &[Element::from(6u8)]).unwrap();
+        assert_eq!(public_inputs, vec![fr(6)]);
+        assert!(prover.verify(&proof, &public_inputs).unwrap());
+    }
+
+    #[test]
+    fn missing_intermediate_wires_are_listed() {
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let t = builder.wire();
+        let u = builder.wire();
+        let z = builder.wire();
+        builder.assert_product(&Expression::from(&x), &Expression::from(&x), &Expression::from(&t));
+        builder.assert_product(&Expression::from(&t), &Expression::from(&x), &Expression::from(&u));
+        builder.assert_product(&Expression::from(&u), &Expression::from(&x), &Expression::from(&z));
+
+        let mut witness_map = BTreeMap::new();
+        witness_map.insert(x.index, fr(2));
+        witness_map.insert(z.index, fr(16));
+        let circuit: ProductCircuit = WrappedCircuit::new(builder.build(), witness_map, vec![z]);
+        assert_eq!(circuit.require_full_witness(true), Err(vec![t, u]));
+
+        let mut witness_map = circuit.witness_map().clone();
+        witness_map.insert(t.index, fr(4));
+        witness_map.insert(u.index, fr(8));
+        let circuit = circuit.with_witness_map(witness_map);
+        assert_eq!(circuit.require_full_witness(true), Ok(()));
+
+        let mut witness_map = circuit.witness_map().clone();
+        witness_map.remove(&z.index);
+        let circuit = circuit.with_witness_map(witness_map);
+        assert_eq!(circuit.require_full_witness(true), Err(vec![z]));
+        assert_eq!(circuit.require_full_witness(false), Ok(()));
+    }
+
+    #[test]
+    fn wire_values_satisfy_r1cs_constraints() {
+        let circuit = product_circuit(product_witness(2, 3, 6));
+        let values = circuit.wire_values_from_witness().unwrap();
+        assert_eq!(values.get(r1cs::Wire { index: 3 }), &Element::from(6u8));
+        assert!(circuit.gadget().constraints.iter().all(|constraint| constraint.evaluate(&values)));
+        assert_eq!(circuit.check_satisfied_in_field(&values), Ok(()));
+
+        let circuit = product_circuit(product_witness(2, 3, 7));
+        let values = circuit.wire_values_from_witness().unwrap();
+        assert!(!circuit.gadget().constraints[0].evaluate(&values));
+    }
+
+    #[test]
+    fn prove_with_montgomery_witness() {
+        let rng = &mut thread_rng();
+        let circuit = product_circuit(BTreeMap::new());
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+
+        let witness = product_witness(2, 3, 6).into_iter()
+            .map(|(index, value)| (index, value.into_raw_repr()))
+            .collect();
+        let gadget = || r1cs::Gadget {
+            constraints: circuit.gadget().constraints.clone(),
+            witness_generators: Vec::new(),
+        };
+        let z = r1cs::Wire { index: 3 };
+        let montgomery = ProductCircuit::from_montgomery_witness(gadget(), witness, vec![z]).unwrap();
+        assert_eq!(montgomery.witness_map(), &product_witness(2, 3, 6));
+        let proof = prover.prove_circuit(montgomery, rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(6)]).unwrap());
+
+        let mut witness = BTreeMap::new();
+        witness.insert(1, Fr::char());
+        assert!(ProductCircuit::from_montgomery_witness(gadget(), witness, vec![z]).is_err());
+    }
+
+    #[test]
+    fn wires_used_out_of_allocation_order() {
+        // Wires are used in a different order from the one they were allocated in, with some
+        // allocated in bulk and one never used: w5 = (w3 + 1) * w1, w2 = w5 * w5.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let w = builder.wires(4);
+        let last = builder.wire();
+        let plus_one = Expression::from(&w[2]) + Expression::from(&r1cs::Wire::ONE);
+        builder.assert_product(&plus_one, &Expression::from(&w[0]), &Expression::from(&last));
+        builder.assert_product(&Expression::from(&last), &Expression::from(&last), &Expression::from(&w[1]));
+        let circuit: ProductCircuit = WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![w[1]]);
+
+        let mut values = WireValues::new();
+        for (wire, n) in &[(w[0], 2u8), (w[2], 4), (last, 10), (w[1], 100)] {
+            values.set(*wire, Element::from(*n));
+        }
+        let witness_map = circuit.witness_from_wire_values(&values);
+        assert_eq!(witness_map.len(), 4);
+        let circuit = circuit.with_witness_map(witness_map);
+        assert_eq!(circuit.require_full_witness(true), Ok(()));
+        assert_eq!(circuit.check_satisfied(), Ok(()));
+        assert_eq!(circuit.check_satisfied_in_field(&circuit.wire_values_from_witness().unwrap()), Ok(()));
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        let proof = prover.prove_circuit(circuit, rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(100)]).unwrap());
+    }
+
+    #[test]
+    fn witness_from_executed_gadget() {
+        let circuit = product_circuit(BTreeMap::new());
+        let mut values = WireValues::<Bn128F>::new();
+        for (i, n) in [2u8, 3, 6].iter().enumerate() {
+            values.set(r1cs::Wire { index: i as u32 + 1 }, Element::from(*n));
+        }
+        assert_eq!(circuit.witness_from_wire_values(&values), product_witness(2, 3, 6));
+    }
+
+    #[test]
+    fn signed_witness_values() {
+        // (x + y) * 1 = z, with z public.
+        let mut builder = GadgetBuilder::<Bn128F>::new();
+        let x = builder.wire();
+        let y = builder.wire();
+        let z = builder.wire();
+        let sum = Expression::from(&x) + Expression::from(&y);
+        builder.assert_product(&sum, &Expression::from(&r1cs::Wire::ONE), &Expression::from(&z));
+        let circuit: ProductCircuit = WrappedCircuit::new(builder.build(), BTreeMap::new(), vec![z]);
+
+        let signed: BTreeMap<u32, i128> =
+            vec![(x.index, -5), (y.index, 12), (z.index, 7)].into_iter().collect();
+        let witness_map = witness_from_signed_map::<Fr>(&signed);
+        let mut minus_five = fr(5);
+        minus_five.negate();
+        assert_eq!(witness_map[&x.index], minus_five);
+        assert_eq!(witness_map[&y.index], fr(12));
+        let extremes: BTreeMap<u32, i128> = vec![(1, i128::MIN), (2, i128::MAX)].into_iter().collect();
+        let extremes = witness_from_signed_map::<Fr>(&extremes);
+        let mut sum = extremes[&1];
+        sum.add_assign(&extremes[&2]);
+        sum.add_assign(&Fr::one());
+        assert!(sum.is_zero());
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&circuit, rng).unwrap();
+        assert_eq!(circuit.with_witness_map(witness_map.clone()).check_satisfied(), Ok(()));
+        let proof = prover.prove(witness_map, rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(7)]).unwrap());
+        assert!(!prover.verify(&proof, &[fr(17)]).unwrap());
+    }
+}
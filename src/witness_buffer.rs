@@ -0,0 +1,138 @@
+//! Parsing of witnesses computed outside this crate, such as by a witness generator compiled to
+//! WASM.
+//!
+//! The buffer is a dense witness in the same layout as serialized public inputs: a 4-byte
+//! little-endian count `n`, followed by the values of wires `0` to `n - 1`, each as its canonical
+//! integer representation in little-endian byte order, padded to the width of the field's repr
+//! (32 bytes for both BN256 and BLS12-381). Wire 0 is the constant wire, whose value must be one.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::marker::PhantomData;
+use std::path::Path;
+use memmap2::Mmap;
+use pairing::ff::{Field as _, PrimeField, PrimeFieldRepr};
+use r1cs::Wire;
+
+use crate::{witness_key, Error, WitnessSource};
+
+/// Parses a witness buffer into a witness map, checking its length against its count and that
+/// every value is less than the modulus. The constant wire's value is checked, but it isn't
+/// included in the map.
+pub fn witness_from_buffer<Fr: PrimeField>(bytes: &[u8]) -> Result<BTreeMap<u32, Fr>, Error> {
+    let count = validate::<Fr>(bytes)?;
+    (1..count)
+        .map(|index| -> Result<(u32, Fr), Error> { Ok((index as u32, read_value(bytes, index)?)) })
+        .collect()
+}
+
+/// A witness buffer in a memory-mapped file, whose values are read as synthesis looks them up, so
+/// the witness is never held in memory as a whole.
+pub struct MappedWitness<Fr: PrimeField> {
+    map: Mmap,
+    count: usize,
+    _fr: PhantomData<Fr>,
+}
+
+impl<Fr: PrimeField> MappedWitness<Fr> {
+    /// Maps a witness file, and validates it as `witness_from_buffer` does. The file must not be
+    /// modified while it is mapped.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        // Safe as long as the file isn't modified while mapped, as documented.
+        let map = unsafe { Mmap::map(&file)? };
+        let count = validate::<Fr>(&map)?;
+        Ok(Self { map, count, _fr: PhantomData })
+    }
+
+    /// The number of wires the buffer assigns, including the constant wire.
+    pub fn num_wires(&self) -> usize {
+        self.count
+    }
+}
+
+impl<Fr: PrimeField> WitnessSource<Fr> for MappedWitness<Fr> {
+    fn witness(&self, wire: Wire) -> Option<Fr> {
+        let index = witness_key(wire) as usize;
+        if index == 0 || index >= self.count {
+            return None;
+        }
+        read_value(&self.map, index).ok()
+    }
+}
+
+/// Checks a witness buffer's length and values, returning its count.
+fn validate<Fr: PrimeField>(bytes: &[u8]) -> Result<usize, Error> {
+    if bytes.len() < 4 {
+        return Err(invalid("missing the count".into()));
+    }
+    let mut count = [0u8; 4];
+    count.copy_from_slice(&bytes[..4]);
+    let count = u32::from_le_bytes(count) as usize;
+    let expected = count.checked_mul(width::<Fr>()).and_then(|len| len.checked_add(4));
+    if expected != Some(bytes.len()) {
+        return Err(invalid(format!("{} bytes is the wrong length for {} values", bytes.len(), count)));
+    }
+    if count == 0 || read_value::<Fr>(bytes, 0)? != Fr::one() {
+        return Err(invalid("the constant wire's value must be one".into()));
+    }
+    for index in 1..count {
+        read_value::<Fr>(bytes, index)?;
+    }
+    Ok(count)
+}
+
+fn read_value<Fr: PrimeField>(bytes: &[u8], index: usize) -> Result<Fr, Error> {
+    let offset = 4 + index * width::<Fr>();
+    let mut repr = Fr::Repr::default();
+    repr.read_le(&bytes[offset..offset + width::<Fr>()])?;
+    Fr::from_repr(repr).map_err(|e| invalid(e.to_string()))
+}
+
+fn width<Fr: PrimeField>() -> usize {
+    Fr::Repr::default().as_ref().len() * 8
+}
+
+fn invalid(reason: String) -> Error {
+    Error::InvalidFormat(format!("witness buffer: {}", reason))
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use std::collections::BTreeMap;
+    use pairing::compact_bn256::Fr;
+    use pairing::ff::{Field, PrimeField, PrimeFieldRepr};
+    use rand::thread_rng;
+
+    use crate::{serialize_public_inputs, witness_from_buffer, Groth16Prover};
+    use crate::test_util::{fr, product_circuit, product_witness};
+
+    #[test]
+    fn parse_buffer_and_prove() {
+        // What a witness generator would output for x * y = z, with the constant wire first.
+        let buffer = serialize_public_inputs(&[Fr::one(), fr(2), fr(3), fr(6)]);
+        let witness_map = witness_from_buffer::<Fr>(&buffer).unwrap();
+        assert_eq!(witness_map, product_witness(2, 3, 6));
+
+        let rng = &mut thread_rng();
+        let prover = Groth16Prover::setup(&product_circuit(BTreeMap::new()), rng).unwrap();
+        let proof = prover.prove(witness_map, rng).unwrap();
+        assert!(prover.verify(&proof, &[fr(6)]).unwrap());
+    }
+
+    #[test]
+    fn rejects_malformed_buffers() {
+        let buffer = serialize_public_inputs(&[Fr::one(), fr(2)]);
+        assert!(witness_from_buffer::<Fr>(&buffer[..buffer.len() - 1]).is_err());
+        assert!(witness_from_buffer::<Fr>(&buffer[..2]).is_err());
+        assert!(witness_from_buffer::<Fr>(&serialize_public_inputs(&[fr(2)])).is_err());
+
+        // The modulus itself isn't canonical.
+        let mut buffer = buffer;
+        let len = buffer.len();
+        let mut modulus = vec![];
+        Fr::char().write_le(&mut modulus).unwrap();
+        buffer[len - 32..].copy_from_slice(&modulus);
+        assert!(witness_from_buffer::<Fr>(&buffer).is_err());
+    }
+}
@@ -0,0 +1,55 @@
+//! Exercises the verification API using only items which are built without the `prover` feature,
+//! so that `cargo test --no-default-features` checks that a verifier-only build is usable. The
+//! proof being verified is made with bellman directly.
+
+use std::collections::BTreeMap;
+use bellman::groth16::{create_random_proof, generate_random_parameters};
+use pairing::compact_bn256::Bn256;
+use r1cs::{Element, Expression, GadgetBuilder, Bn128 as Bn128F};
+use r1cs_bellman::{public_inputs_for_verification, validate_proof_format, Bn256Converter};
+use r1cs_bellman::{FieldConverter, ProofEnvelope, VerifyingKeyBundle, WrappedCircuit};
+use rand::thread_rng;
+
+#[test]
+fn verify_without_prover() {
+    let mut builder = GadgetBuilder::<Bn128F>::new();
+    let x = builder.wire();
+    let y = builder.wire();
+    let z = builder.wire();
+    builder.assert_product(&Expression::from(&x), &Expression::from(&y), &Expression::from(&z));
+    let gadget = builder.build();
+    let mut witness_map = BTreeMap::new();
+    for (wire, n) in &[(x, 2u8), (y, 3), (z, 6)] {
+        witness_map.insert(wire.index, Bn256Converter::convert_field(&Element::from(*n)));
+    }
+    let circuit: WrappedCircuit<Bn128F, Bn256, Bn256Converter> =
+        WrappedCircuit::new(gadget, witness_map, vec![z]);
+
+    let rng = &mut thread_rng();
+    let params = generate_random_parameters(circuit.with_witness_map(BTreeMap::new()), rng).unwrap();
+    let proving_circuit = circuit.with_witness_map(circuit.witness_map().clone());
+    let proof = create_random_proof(proving_circuit, &params, rng).unwrap();
+
+    // What a verifier service receives: the verifying key bundle and an enveloped proof.
+    let bundle = VerifyingKeyBundle {
+        fingerprint: circuit.fingerprint(),
+        num_public_inputs: 1,
+        vk: params.vk.clone(),
+    };
+    let mut bundle_bytes = vec![];
+    bundle.write(&mut bundle_bytes).unwrap();
+    let mut envelope_bytes = vec![];
+    ProofEnvelope::new(&proof).write(&mut envelope_bytes).unwrap();
+
+    let bundle = VerifyingKeyBundle::<Bn256>::read(&bundle_bytes[..]).unwrap();
+    let envelope = ProofEnvelope::read(&envelope_bytes[..]).unwrap();
+    let mut proof_bytes = vec![];
+    proof.write(&mut proof_bytes).unwrap();
+    assert_eq!(validate_proof_format::<Bn256>(&proof_bytes), Ok(()));
+    let public_inputs = |n: u8| {
+        public_inputs_for_verification::<Bn128F, Bn256, Bn256Converter>(&[Element::from(n)]).unwrap()
+    };
+    let verifier = bundle.verifier();
+    assert!(verifier.verify_envelope(&envelope, &public_inputs(6)).unwrap());
+    assert!(!verifier.verify_envelope(&envelope, &public_inputs(7)).unwrap());
+}